@@ -1,21 +1,61 @@
+use std::collections::HashSet;
 use std::convert::TryInto;
 
 use super::super::board::{
-    self, BoardEvent, BoardEventProcessor, Boardable, Octi, OctiID, Position, Team,
+    self, Arrow, ArrowStatus, BoardBounds, BoardEvent, BoardEventProcessor, Boardable, Octi,
+    OctiID, OctiMove, Position, Team,
 };
 
-use super::{team_index, BOARD_HEIGHT, BOARD_WIDTH, TEAMS};
+use super::moveiter::new_octi_move_iterator;
+use super::{opponent, team_index, winner, BOARD_HEIGHT, BOARD_WIDTH, TEAMS};
+
+// what do_move changed, so undo_move can restore it, moves are undone in reverse order
+pub struct UndoStack(Vec<Change>);
+
+#[derive(Clone, Copy)]
+enum Change {
+    // a move starts here, with the turn and arrow counts before it
+    Move(Team, [u32; TEAMS]),
+    // a square and the octi it held before the move
+    Square(usize, Option<Octi>),
+}
+
+// a transformation of the board that maps every position to an equivalent one, the rules of
+// the game don't change under any of them
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Symmetry {
+    Identity,
+    // left and right swapped
+    Mirror,
+    // the teams swapped and the board turned upside down, so both still play towards each other,
+    // scores from red's perspective are negated
+    ColourSwap,
+    MirrorColourSwap,
+}
+
+// a board without octi ids, positions that only differ in ids have the same key, keys of boards
+// with different bounds aren't meant to be compared
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoardKey {
+    turn: Team,
+    // 0 for an empty square, otherwise 1 | team << 1 | arrows << 2
+    squares: [u16; BOARD_WIDTH * BOARD_HEIGHT],
+    arr_counts: [u32; TEAMS],
+}
 
 // optimized board for calculations
 // octis indexed by pos and not id because pos is used more often
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Board {
     turn: Team,
+    // reduced boards start at (0, 0) and only use part of the squares
+    bounds: BoardBounds,
     octis: [Option<Octi>; BOARD_WIDTH * BOARD_HEIGHT],
     arr_counts: [u32; TEAMS],
 }
 
 impl Board {
+    // the bounds of board are assumed to start at (0, 0) and to fit the full board
     pub fn new(board: &board::Board) -> Board {
         let octis: [usize; BOARD_WIDTH * BOARD_HEIGHT] = (0..BOARD_WIDTH * BOARD_HEIGHT)
             .collect::<Vec<_>>()
@@ -31,65 +71,184 @@ impl Board {
 
         Board {
             turn: board.turn(),
+            bounds: board.bounds(),
             octis,
             arr_counts,
         }
     }
 
+    // a full board, octi positions are assumed to be in bounds and distinct
+    pub fn from_octis(turn: Team, octis: &[Octi], arr_counts: [u32; TEAMS]) -> Board {
+        let mut board = Board {
+            turn,
+            bounds: BoardBounds::default(),
+            octis: [None; BOARD_WIDTH * BOARD_HEIGHT],
+            arr_counts,
+        };
+        for octi in octis {
+            board.insert_octi_at_pos(&octi.pos(), *octi);
+        }
+        board
+    }
+
+    pub fn bounds(&self) -> BoardBounds {
+        self.bounds
+    }
+
     pub fn octis(&self) -> impl Iterator<Item = &Octi> {
         self.octis.iter().filter_map(|x| x.as_ref())
     }
 
-    pub fn horizontal_flip(&self) -> Board {
-        Board {
-            turn: self.turn,
-            octis: (0..BOARD_WIDTH * BOARD_HEIGHT).map(|i| {
-                let pos = Self::index_to_pos(i);
-                let new_index = Self::pos_to_index(&Position::new(BOARD_WIDTH as i32 - pos.x() - 1, pos.y()));
-                let octi = self.octis[new_index];
-                if let Some(mut octi) = octi {
-                    octi.flip();
+    // the position the symmetry maps this one to
+    pub fn transform(&self, symmetry: Symmetry) -> Board {
+        let mut arr_counts = self.arr_counts;
+        if symmetry.swaps_colours() {
+            arr_counts.reverse();
+        }
+
+        let mut board = Board {
+            turn: symmetry.map_team(self.turn),
+            bounds: self.bounds,
+            octis: [None; BOARD_WIDTH * BOARD_HEIGHT],
+            arr_counts,
+        };
+        for octi in self.octis() {
+            let octi = symmetry.map_octi(octi, self.bounds);
+            board.insert_octi_at_pos(&octi.pos(), octi);
+        }
+        board
+    }
+
+    // the position of a key, octi ids are given in square order
+    pub fn from_key(key: &BoardKey, bounds: BoardBounds) -> Board {
+        let mut board = Board {
+            turn: key.turn,
+            bounds,
+            octis: [None; BOARD_WIDTH * BOARD_HEIGHT],
+            arr_counts: key.arr_counts,
+        };
+        let mut id = 0;
+        for (index, square) in key.squares.iter().enumerate() {
+            if square & 1 == 0 {
+                continue;
+            }
+
+            let team = if (square >> 1) & 1 == 0 {
+                Team::Red
+            } else {
+                Team::Green
+            };
+            let arrs = [0, 1, 2, 3, 4, 5, 6, 7].map(|x| {
+                if (square >> 2) & (1 << x) != 0 {
+                    ArrowStatus::Active
+                } else {
+                    ArrowStatus::Inactive
                 }
-                octi
-            }).collect::<Vec<_>>().try_into().unwrap(),
-            arr_counts: self.arr_counts
+            });
+            board.octis[index] = Some(Octi::new(id, team, Self::index_to_pos(index), arrs));
+            id += 1;
         }
+        board
     }
 
-    pub fn vertical_flip(&self) -> Board {
-        Board {
+    // the position without octi ids
+    pub fn key(&self) -> BoardKey {
+        BoardKey {
             turn: self.turn,
-            octis: (0..BOARD_WIDTH * BOARD_HEIGHT).map(|i| {
-                let pos = Self::index_to_pos(i);
-                let new_index = Self::pos_to_index(&Position::new(pos.x(), BOARD_HEIGHT as i32 - pos.y() - 1));
-                self.octis[new_index]
-            }).collect::<Vec<_>>().try_into().unwrap(),
-            arr_counts: self.arr_counts
+            squares: self.octis.map(|cell| match cell {
+                Some(octi) => {
+                    let arrs = octi
+                        .arr_iter()
+                        .filter(|(_, x)| **x == ArrowStatus::Active)
+                        .fold(0, |acc, (i, _)| acc | 1 << i);
+                    1 | (team_index(octi.team()) as u16) << 1 | arrs << 2
+                }
+                None => 0,
+            }),
+            arr_counts: self.arr_counts,
         }
     }
 
-    pub fn switch_colors(&mut self) {
+    // the smallest key of the positions the symmetries map this one to, with the symmetry giving
+    // it, every position related by the symmetries has the same canonical key
+    //
+    // symmetries has to contain the identity and be closed under composition
+    pub fn canonical_key(&self, symmetries: &[Symmetry]) -> (BoardKey, Symmetry) {
+        symmetries
+            .iter()
+            .map(|x| (self.transform(*x).key(), *x))
+            .min_by_key(|x| x.0)
+            .unwrap()
+    }
+
+    // applies a legal move without validating it or allocating, the move is pushed onto undo
+    pub fn do_move(&mut self, octi_move: &OctiMove, undo: &mut UndoStack) {
+        undo.0.push(Change::Move(self.turn, self.arr_counts));
+
+        match octi_move {
+            OctiMove::Arrow(pos, arr) => {
+                let index = Self::pos_to_index(pos);
+                undo.0.push(Change::Square(index, self.octis[index]));
+
+                let octi = self.octis[index].as_mut().unwrap();
+                octi.add_arr(*arr);
+                self.arr_counts[team_index(octi.team())] -= 1;
+            }
+            OctiMove::Move(pos, arrs) => {
+                let index = Self::pos_to_index(pos);
+                undo.0.push(Change::Square(index, self.octis[index]));
+                let mut octi = self.octis[index].take().unwrap();
+
+                let mut next_pos = *pos;
+                let (arr, is_capture) = arrs[0];
+                let simple_pos = next_pos + arr.direction();
+
+                if arrs.len() == 1
+                    && !is_capture
+                    && self.in_bounds(&simple_pos)
+                    && self.get_octi_by_pos(&simple_pos).is_none()
+                {
+                    next_pos = simple_pos;
+                } else {
+                    for (arr, is_capture) in arrs {
+                        let in_between_pos = next_pos + arr.direction();
+                        next_pos = next_pos + arr.direction() * 2;
+
+                        if *is_capture {
+                            let in_between_index = Self::pos_to_index(&in_between_pos);
+                            undo.0
+                                .push(Change::Square(in_between_index, self.octis[in_between_index]));
+                            let eaten = self.octis[in_between_index].take().unwrap();
+                            self.arr_counts[team_index(octi.team())] += eaten.arr_count();
+                        }
+                    }
+                }
+
+                let next_index = Self::pos_to_index(&next_pos);
+                undo.0.push(Change::Square(next_index, self.octis[next_index]));
+                octi.set_pos(next_pos);
+                self.octis[next_index] = Some(octi);
+            }
+        }
+
         self.turn = match self.turn {
             Team::Red => Team::Green,
             Team::Green => Team::Red,
         };
+    }
 
-        self.octis.iter_mut().for_each(|cell| {
-            match cell {
-                Some(octi) => {
-                    *octi = Octi::new(
-                        octi.id(),
-                        match octi.team() {
-                            Team::Red => Team::Green,
-                            Team::Green => Team::Red
-                        },
-                        octi.pos(),
-                        octi.arrs()
-                    )
-                },
-                None => {},
+    // takes back the last move pushed onto undo
+    pub fn undo_move(&mut self, undo: &mut UndoStack) {
+        while let Some(change) = undo.0.pop() {
+            match change {
+                Change::Square(index, octi) => self.octis[index] = octi,
+                Change::Move(turn, arr_counts) => {
+                    self.turn = turn;
+                    self.arr_counts = arr_counts;
+                    return;
+                }
             }
-        })
+        }
     }
 
     // mutability functions
@@ -158,10 +317,7 @@ impl Boardable for Board {
     }
 
     fn in_bounds(&self, pos: &Position) -> bool {
-        0 <= pos.x()
-            && pos.x() < BOARD_WIDTH as i32
-            && 0 <= pos.y()
-            && pos.y() < BOARD_HEIGHT as i32
+        self.bounds.in_bounds(pos)
     }
 
     fn turn(&self) -> Team {
@@ -202,3 +358,273 @@ impl BoardEventProcessor for Board {
         }
     }
 }
+
+impl BoardKey {
+    // the turn, the arrow counts and the squares within bounds, little endian
+    pub fn to_bytes(self, bounds: BoardBounds) -> Vec<u8> {
+        let mut bytes = vec![team_index(self.turn) as u8];
+        for arr_count in self.arr_counts {
+            bytes.extend(arr_count.to_le_bytes());
+        }
+        for index in Self::indices(bounds) {
+            bytes.extend(self.squares[index].to_le_bytes());
+        }
+        bytes
+    }
+
+    // bytes has to be as long as to_bytes makes them for bounds
+    pub fn from_bytes(bytes: &[u8], bounds: BoardBounds) -> Result<BoardKey, String> {
+        if bytes.len() != Self::byte_len(bounds) {
+            Err(format!("Invalid key length: {}", bytes.len()))?;
+        }
+
+        let turn = match bytes[0] {
+            0 => Team::Red,
+            1 => Team::Green,
+            x => Err(format!("Invalid turn: {}", x))?,
+        };
+        let mut arr_counts = [0; TEAMS];
+        for (i, arr_count) in arr_counts.iter_mut().enumerate() {
+            *arr_count = u32::from_le_bytes(bytes[1 + 4 * i..5 + 4 * i].try_into().unwrap());
+        }
+        let mut squares = [0; BOARD_WIDTH * BOARD_HEIGHT];
+        for (i, index) in Self::indices(bounds).enumerate() {
+            let start = 1 + 4 * TEAMS + 2 * i;
+            squares[index] = u16::from_le_bytes([bytes[start], bytes[start + 1]]);
+        }
+
+        Ok(BoardKey {
+            turn,
+            squares,
+            arr_counts,
+        })
+    }
+
+    pub fn byte_len(bounds: BoardBounds) -> usize {
+        1 + 4 * TEAMS + 2 * Self::indices(bounds).count()
+    }
+
+    // the board indices of the squares within bounds
+    fn indices(bounds: BoardBounds) -> impl Iterator<Item = usize> {
+        (0..bounds.height() as usize)
+            .flat_map(move |y| (0..bounds.width() as usize).map(move |x| x + y * BOARD_WIDTH))
+    }
+}
+
+impl UndoStack {
+    pub fn new() -> UndoStack {
+        UndoStack(Vec::new())
+    }
+}
+
+impl Default for UndoStack {
+    fn default() -> Self {
+        UndoStack::new()
+    }
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 4] = [
+        Symmetry::Identity,
+        Symmetry::Mirror,
+        Symmetry::ColourSwap,
+        Symmetry::MirrorColourSwap,
+    ];
+
+    // Getters
+
+    pub fn mirrors(&self) -> bool {
+        matches!(self, Symmetry::Mirror | Symmetry::MirrorColourSwap)
+    }
+
+    pub fn swaps_colours(&self) -> bool {
+        matches!(self, Symmetry::ColourSwap | Symmetry::MirrorColourSwap)
+    }
+
+    // Operations
+
+    // every symmetry is its own inverse, so these map both ways, positions are mapped within bounds
+    // starting at (0, 0)
+
+    pub fn map_team(&self, team: Team) -> Team {
+        if self.swaps_colours() {
+            opponent(team)
+        } else {
+            team
+        }
+    }
+
+    pub fn map_pos(&self, pos: Position, bounds: BoardBounds) -> Position {
+        let x = if self.mirrors() {
+            bounds.width() - pos.x() - 1
+        } else {
+            pos.x()
+        };
+        let y = if self.swaps_colours() {
+            bounds.height() - pos.y() - 1
+        } else {
+            pos.y()
+        };
+        Position::new(x, y)
+    }
+
+    pub fn map_arrow(&self, arr: Arrow) -> Arrow {
+        let arr = if self.mirrors() {
+            arr.horizontal_flip()
+        } else {
+            arr
+        };
+        if self.swaps_colours() {
+            arr.vertical_flip()
+        } else {
+            arr
+        }
+    }
+
+    pub fn map_octi(&self, octi: &Octi, bounds: BoardBounds) -> Octi {
+        let mut mapped = Octi::new(
+            octi.id(),
+            self.map_team(octi.team()),
+            self.map_pos(octi.pos(), bounds),
+            octi.arrs(),
+        );
+        if self.mirrors() {
+            mapped.horizontal_flip();
+        }
+        if self.swaps_colours() {
+            mapped.vertical_flip();
+        }
+        mapped
+    }
+
+    pub fn map_move(&self, octi_move: &OctiMove, bounds: BoardBounds) -> OctiMove {
+        match octi_move {
+            OctiMove::Arrow(pos, arr) => {
+                OctiMove::Arrow(self.map_pos(*pos, bounds), self.map_arrow(*arr))
+            }
+            OctiMove::Move(pos, arrs) => OctiMove::Move(
+                self.map_pos(*pos, bounds),
+                arrs.iter()
+                    .map(|(arr, is_capture)| (self.map_arrow(*arr), *is_capture))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+// checks on every position up to depth that the symmetries map it to positions with the same
+// canonical key, the mapped moves and the mapped winner
+pub fn check_symmetries(board: &Board, depth: u32) -> Result<(), String> {
+    let octi_moves = new_octi_move_iterator(board).collect::<Vec<_>>();
+    let canonical_key = board.canonical_key(&Symmetry::ALL).0;
+
+    for symmetry in Symmetry::ALL {
+        let mapped = board.transform(symmetry);
+        if mapped.transform(symmetry).key() != board.key() {
+            Err(format!("{:?} is not its own inverse", symmetry))?;
+        }
+        if mapped.canonical_key(&Symmetry::ALL).0 != canonical_key {
+            Err(format!("{:?} changes the canonical key", symmetry))?;
+        }
+        if winner(&mapped) != winner(board).map(|x| symmetry.map_team(x)) {
+            Err(format!("{:?} changes the winner", symmetry))?;
+        }
+
+        let mapped_moves = new_octi_move_iterator(&mapped).collect::<HashSet<_>>();
+        let expected_moves = octi_moves
+            .iter()
+            .map(|x| symmetry.map_move(x, board.bounds))
+            .collect::<HashSet<_>>();
+        if mapped_moves != expected_moves {
+            Err(format!(
+                "{:?} doesn't map the moves to the mapped moves",
+                symmetry
+            ))?;
+        }
+    }
+
+    if depth == 0 || winner(board).is_some() {
+        return Ok(());
+    }
+
+    let mut board = *board;
+    let mut undo = UndoStack::new();
+    for octi_move in octi_moves {
+        board.do_move(&octi_move, &mut undo);
+        check_symmetries(&board, depth - 1)?;
+        board.undo_move(&mut undo);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the start, positions from engine games and a reduced board, with the positions a move away
+    // from them
+    fn positions() -> Vec<Board> {
+        let mut boards = Vec::new();
+        for position in [
+            "6/1GGGG1/6/6/6/1RRRR1/6 r 12 12",
+            "6/1GGG[46]G1/6/6/4R[12]1/1RR1R1/6 g 10 10",
+            "6/1G[16]G[46]G[06]G[06]1/6/6/4R[1]1/1R[013]R[4]1R[25]1/6 r 5 4",
+            "6/2G[4567]2G[0456]/2G[067]3/5R[12]/2R[0123]3/2R[1234]1R[23]1/6 g 0 1",
+            "4/1G[5]G1/4/1R[1]R[26]1/4 r 1 2",
+        ] {
+            let position: board::Board = position.parse().unwrap();
+            let board = Board::new(&position);
+            boards.push(board);
+
+            let mut board = board;
+            let mut undo = UndoStack::new();
+            for octi_move in new_octi_move_iterator(&board).collect::<Vec<_>>() {
+                board.do_move(&octi_move, &mut undo);
+                boards.push(board);
+                board.undo_move(&mut undo);
+            }
+        }
+        boards
+    }
+
+    #[test]
+    fn symmetries_are_their_own_inverses() {
+        for board in positions() {
+            for symmetry in Symmetry::ALL {
+                assert!(board.transform(symmetry).transform(symmetry).key() == board.key());
+            }
+        }
+
+        let bounds = positions()[0].bounds();
+        for symmetry in Symmetry::ALL {
+            for x in 0..BOARD_WIDTH as i32 {
+                for y in 0..BOARD_HEIGHT as i32 {
+                    let pos = Position::new(x, y);
+                    assert_eq!(symmetry.map_pos(symmetry.map_pos(pos, bounds), bounds), pos);
+                }
+            }
+            for i in 0..8 {
+                let arr = Arrow::new(i).unwrap();
+                assert_eq!(symmetry.map_arrow(symmetry.map_arrow(arr)).value(), i);
+            }
+        }
+    }
+
+    #[test]
+    fn transforms_share_the_canonical_key() {
+        for board in positions() {
+            let (key, symmetry) = board.canonical_key(&Symmetry::ALL);
+            assert!(board.transform(symmetry).key() == key);
+            for symmetry in Symmetry::ALL {
+                assert!(board.transform(symmetry).canonical_key(&Symmetry::ALL).0 == key);
+            }
+        }
+    }
+
+    #[test]
+    fn symmetries_map_moves_and_children() {
+        for board in positions() {
+            check_symmetries(&board, 1).unwrap();
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::super::board::{self, BoardEventProcessor, Boardable, OctiMove, Team};
+
+use super::board::Board;
+use super::eval::{Evaluator, Value};
+use super::priority::MoveOrderer;
+use super::{score_moves, MinimaxConfig};
+
+// an opening book file has one line per book move, with tab separated fields:
+//
+// position  move  weight
+//
+// positions are in position notation, so octi ids don't matter when looking a position up
+
+pub struct Book {
+    entries: HashMap<String, Vec<(OctiMove, u32)>>,
+}
+
+pub struct BookSearchConfig {
+    pub plies: usize,
+    pub depth: u32,
+    pub width: usize,
+    // in eval points, how much worse than the best move a move can score and still be booked
+    pub margin: i32,
+}
+
+impl Book {
+    pub fn new() -> Book {
+        Book {
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Book, Box<dyn Error>> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+
+        let mut book = Book::new();
+        for (i, line) in s.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split('\t').collect();
+            let entry = (|| -> Result<(board::Board, OctiMove, u32), String> {
+                if fields.len() != 3 {
+                    Err(format!("Invalid number of fields: {}", fields.len()))?;
+                }
+                let position: board::Board = fields[0].parse()?;
+                let octi_move: OctiMove = fields[1].parse()?;
+                let weight = fields[2]
+                    .parse()
+                    .map_err(|_| format!("Invalid weight: {}", fields[2]))?;
+                if !position.is_move_valid(&octi_move) {
+                    Err(format!("Illegal move: {}", octi_move))?;
+                }
+                Ok((position, octi_move, weight))
+            })()
+            .map_err(|e| format!("Invalid line {} in {}: {}", i + 1, path.display(), e))?;
+
+            book.add(&entry.0, entry.1, entry.2);
+        }
+
+        Ok(book)
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let mut positions = self.entries.keys().collect::<Vec<_>>();
+        positions.sort();
+
+        let mut s = String::new();
+        for position in positions {
+            for (octi_move, weight) in &self.entries[position] {
+                s.push_str(&format!("{}\t{}\t{}\n", position, octi_move, weight));
+            }
+        }
+        fs::write(path, s)?;
+        Ok(())
+    }
+
+    // Getters
+
+    pub fn position_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn moves(&self, position: &board::Board) -> Option<&[(OctiMove, u32)]> {
+        self.entries.get(&position.to_string()).map(|x| x.as_slice())
+    }
+
+    // weighted random book move of the position
+    pub fn pick<R: Rng>(&self, position: &board::Board, rng: &mut R) -> Option<OctiMove> {
+        self.moves(position)?
+            .choose_weighted(rng, |x| x.1)
+            .ok()
+            .map(|x| x.0.clone())
+    }
+
+    // Operations
+
+    // adds weight to the move, adding it to the book if it's new
+    pub fn add(&mut self, position: &board::Board, octi_move: OctiMove, weight: u32) {
+        let moves = self.entries.entry(position.to_string()).or_default();
+        match moves.iter_mut().find(|x| x.0 == octi_move) {
+            Some(entry) => entry.1 += weight,
+            None => moves.push((octi_move, weight)),
+        }
+    }
+
+    // adds the first plies moves of a game played from the default board, moves of the winner
+    // count double and moves of the loser are left out
+    pub fn add_game(
+        &mut self,
+        moves: &[OctiMove],
+        winner: Option<Team>,
+        plies: usize,
+    ) -> Result<(), String> {
+        let mut position = board::Board::default();
+
+        for octi_move in moves.iter().take(plies) {
+            let weight = match winner {
+                Some(team) if team == position.turn() => 2,
+                Some(_) => 0,
+                None => 1,
+            };
+            if weight > 0 {
+                self.add(&position, octi_move.clone(), weight);
+            }
+            position.make_move(octi_move)?;
+        }
+
+        Ok(())
+    }
+
+    // books every move of the position that scores within margin of the best move (at most width
+    // of them), then does the same for the positions they lead to, plies deep
+    pub fn add_searched<E: Evaluator, O: MoveOrderer>(
+        &mut self,
+        position: &board::Board,
+        config: &BookSearchConfig,
+        evaluator: &E,
+        orderer: &O,
+    ) -> Result<(), Box<dyn Error>> {
+        self._add_searched(position, config.plies, config, evaluator, orderer)
+    }
+
+    fn _add_searched<E: Evaluator, O: MoveOrderer>(
+        &mut self,
+        position: &board::Board,
+        plies: usize,
+        config: &BookSearchConfig,
+        evaluator: &E,
+        orderer: &O,
+    ) -> Result<(), Box<dyn Error>> {
+        if plies == 0 || self.moves(position).is_some() {
+            return Ok(());
+        }
+
+        let minimax_config = MinimaxConfig {
+            depth: config.depth,
+            ..MinimaxConfig::default()
+        };
+        let scores = score_moves(&Board::new(position), &minimax_config, evaluator, orderer, None)?;
+        let Some(best) = scores.first().map(|x| x.1.value()) else {
+            return Ok(());
+        };
+
+        let candidates = scores
+            .into_iter()
+            .take(config.width)
+            .filter(|x| within_margin(best, x.1.value(), config.margin))
+            .collect::<Vec<_>>();
+
+        // better moves get higher weights
+        let count = candidates.len() as u32;
+        for (rank, (octi_move, _)) in candidates.iter().enumerate() {
+            self.add(position, octi_move.clone(), count - rank as u32);
+        }
+
+        for (octi_move, _) in candidates {
+            let mut next_position = position.clone();
+            next_position.make_move(&octi_move)?;
+            self._add_searched(&next_position, plies - 1, config, evaluator, orderer)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn within_margin(best: Value, value: Value, margin: i32) -> bool {
+    match (best, value) {
+        (Value::Score(best), Value::Score(value)) => (best - value).abs() <= margin,
+        _ => best == value,
+    }
+}
+
+impl Default for Book {
+    fn default() -> Self {
+        Book::new()
+    }
+}
@@ -1,17 +1,23 @@
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::error::Error;
+use std::fmt::Display;
 use std::fs::File;
 use std::io::BufReader;
+use std::iter;
 use std::ops::Neg;
+use std::path::Path;
+use std::str::FromStr;
 
-use super::super::board::{ArrowStatus, BoardEventProcessor, Boardable, OctiMove, Team};
+use super::super::board::{Arrow, ArrowStatus, BoardBounds, Boardable, OctiMove, Position, Team};
 use super::super::global::ARROWS_PER_OCTI;
 
-use super::board::Board;
+use super::board::{Board, Symmetry, UndoStack};
 use super::matrix::Matrix;
 use super::moveiter::new_move_octi_move_iterator;
-use super::{team_index, winner, TEAMS};
+use super::{team_index, winner, BOARD_HEIGHT, BOARD_WIDTH, TEAMS};
+
+const DEFAULT_EVAL_DATA: &str = include_str!("data/default_eval_data.json");
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Value {
@@ -19,88 +25,158 @@ pub enum Value {
     Win(Team),
 }
 
+// static evaluation used at the leaves of a search, scores are from red's perspective
+pub trait Evaluator {
+    fn evaluate(&self, board: &Board) -> Value;
+
+    // the symmetries the evaluation doesn't change under, besides the colour swap negating it,
+    // the search shares its table entries between positions they relate
+    fn symmetries(&self) -> Vec<Symmetry> {
+        vec![Symmetry::Identity]
+    }
+}
+
+// a single parameter of EvalData that a position is scored with
+#[derive(Clone, Copy)]
+pub enum Term {
+    Octi,
+    Arrow(usize),
+    Position(Position),
+    SimpleMove(Position),
+    JumpMove(Position),
+}
+
+impl Term {
+    // eval data is made for the full board
+    fn transform(&self, symmetry: Symmetry) -> Term {
+        let bounds = BoardBounds::default();
+        match self {
+            Term::Octi => Term::Octi,
+            Term::Arrow(i) => Term::Arrow(symmetry.map_arrow(Arrow::new(*i).unwrap()).value()),
+            Term::Position(pos) => Term::Position(symmetry.map_pos(*pos, bounds)),
+            Term::SimpleMove(pos) => Term::SimpleMove(symmetry.map_pos(*pos, bounds)),
+            Term::JumpMove(pos) => Term::JumpMove(symmetry.map_pos(*pos, bounds)),
+        }
+    }
+}
+
 pub fn board_eval(board: &Board, eval_data: &EvalData) -> Value {
-    let game_winner = winner(board);
+    let mut evals = [0; TEAMS];
+    let game_winner = eval_terms(board, |team, term| {
+        evals[team_index(team)] += eval_data.term_value(team, term);
+    });
+
     match game_winner {
-        Some(game_winner) => return Value::Win(game_winner),
-        None => (),
+        Some(game_winner) => Value::Win(game_winner),
+        None => Value::Score(evals[team_index(Team::Red)] - evals[team_index(Team::Green)]),
     }
+}
+
+// board_eval split into its terms, to see what an evaluation is made of
+pub fn explain_eval(board: &Board, eval_data: &EvalData) -> EvalBreakdown {
+    let mut teams = [TeamEval::default(); TEAMS];
+    let winner = eval_terms(board, |team, term| {
+        let value = eval_data.term_value(team, term);
+        let team_eval = &mut teams[team_index(team)];
+        match term {
+            Term::Octi => team_eval.octis += value,
+            Term::Arrow(i) => team_eval.arrows[i] += value,
+            Term::Position(_) => team_eval.position += value,
+            Term::SimpleMove(_) => team_eval.simple_moves += value,
+            Term::JumpMove(_) => team_eval.jump_moves += value,
+        }
+    });
+
+    EvalBreakdown { teams, winner }
+}
 
-    let mut red_eval = 0;
-    let mut green_eval = 0;
+// calls f for every term board_eval adds to the evaluation of a team, board_eval is linear in
+// these terms which is what the tuner relies on
+//
+// returns the winner if the position is decided, in which case the terms don't matter
+pub fn eval_terms<F: FnMut(Team, Term)>(board: &Board, mut f: F) -> Option<Team> {
+    let game_winner = winner(board);
+    if game_winner.is_some() {
+        return game_winner;
+    }
 
     // add arrow values, intrinsic octi values and position values
     for octi in board.octis() {
         let pos = octi.pos();
         let team = octi.team();
 
-        let eval = match team {
-            Team::Red => &mut red_eval,
-            Team::Green => &mut green_eval,
-        };
-
-        *eval += eval_data.octi_value;
-        let arrow_values = &eval_data.arrow_values[team_index(team)];
+        f(team, Term::Octi);
 
         for (i, arrow) in octi.arr_iter() {
             if *arrow == ArrowStatus::Active {
-                *eval += arrow_values[i];
+                f(team, Term::Arrow(i));
             }
         }
 
-        let position_matricies = &eval_data.position_matricies[team_index(team)];
-        *eval += position_matricies.get(&pos).unwrap();
+        f(team, Term::Position(pos));
     }
 
     let cur_team = board.turn();
+    // moves of both teams are made and taken back on a single copy of the board
+    let mut board = *board;
+    let mut undo = UndoStack::new();
     for team in [Team::Red, Team::Green] {
-        let mut board = board.clone();
         board.set_turn(team);
 
-        for octi_mov in new_move_octi_move_iterator(&board) {
+        let octi_movs = new_move_octi_move_iterator(&board).collect::<Vec<_>>();
+        for octi_mov in octi_movs {
             match &octi_mov {
                 OctiMove::Move(pos, arrs) => {
-                    let mut board_clone = board.clone();
-
-                    let octi = board_clone.get_octi_by_pos(&pos).unwrap();
+                    let octi = board.get_octi_by_pos(pos).unwrap();
                     let team = octi.team();
                     let octi_id = octi.id();
                     let preivous_pos = octi.pos();
 
-                    board_clone.make_move(&octi_mov);
-
-                    let new_pos = board_clone.get_octi_by_id(&octi_id).unwrap().pos();
+                    board.do_move(&octi_mov, &mut undo);
+                    let new_pos = board.get_octi_by_id(&octi_id).unwrap().pos();
+                    let game_winner = winner(&board);
+                    board.undo_move(&mut undo);
 
-                    let eval = match team {
-                        Team::Red => &mut red_eval,
-                        Team::Green => &mut green_eval,
-                    };
                     // if there is a winner
                     // check whether the winner is the original turn team from the original board state
                     // and also that this is a move done by the same team
                     // (opponent team theoretically can do a move which will result in the win of the other, but an optimal opponent won't)
-                    if let Some(game_winner) = winner(&board_clone) {
+                    if let Some(game_winner) = game_winner {
                         if game_winner == cur_team && game_winner == team {
-                            return Value::Win(game_winner);
+                            return Some(game_winner);
                         }
                     }
 
                     let abs_dif = (new_pos - preivous_pos).abs();
-                    let move_matrix = if abs_dif.x() <= 1 && abs_dif.y() <= 1 && arrs.len() == 1 {
-                        &eval_data.simple_move_matricies[team_index(team)]
+                    if abs_dif.x() <= 1 && abs_dif.y() <= 1 && arrs.len() == 1 {
+                        f(team, Term::SimpleMove(new_pos));
                     } else {
-                        &eval_data.jump_move_matricies[team_index(team)]
-                    };
-
-                    *eval += move_matrix.get(&new_pos).unwrap();
+                        f(team, Term::JumpMove(new_pos));
+                    }
                 }
                 _ => panic!("MoveOctiMoveIterator returned non mov octi move"),
             }
         }
     }
 
-    let final_eval = red_eval - green_eval;
-    Value::Score(final_eval)
+    None
+}
+
+// the evaluation of one team, summed per kind of term
+#[derive(Clone, Copy, Default)]
+pub struct TeamEval {
+    pub octis: i32,
+    // indexed by arrow direction
+    pub arrows: [i32; ARROWS_PER_OCTI],
+    pub position: i32,
+    pub simple_moves: i32,
+    pub jump_moves: i32,
+}
+
+pub struct EvalBreakdown {
+    teams: [TeamEval; TEAMS],
+    // a decided position is a win whatever its terms add up to
+    winner: Option<Team>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -144,6 +220,30 @@ impl Ord for Value {
     }
 }
 
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Score(score) => write!(f, "{}", score),
+            Value::Win(Team::Red) => write!(f, "win_red"),
+            Value::Win(Team::Green) => write!(f, "win_green"),
+        }
+    }
+}
+
+impl FromStr for Value {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "win_red" => Ok(Value::Win(Team::Red)),
+            "win_green" => Ok(Value::Win(Team::Green)),
+            _ => Ok(Value::Score(
+                s.parse().map_err(|_| format!("Invalid value: {}", s))?,
+            )),
+        }
+    }
+}
+
 impl Neg for Value {
     type Output = Self;
 
@@ -158,9 +258,231 @@ impl Neg for Value {
     }
 }
 
+impl TeamEval {
+    pub fn total(&self) -> i32 {
+        self.octis
+            + self.arrows.iter().sum::<i32>()
+            + self.position
+            + self.simple_moves
+            + self.jump_moves
+    }
+}
+
+impl EvalBreakdown {
+    // Getters
+
+    pub fn team(&self, team: Team) -> &TeamEval {
+        &self.teams[team_index(team)]
+    }
+
+    // Operations
+
+    // the same value board_eval gives the position
+    pub fn value(&self) -> Value {
+        match self.winner {
+            Some(team) => Value::Win(team),
+            None => Value::Score(self.team(Team::Red).total() - self.team(Team::Green).total()),
+        }
+    }
+}
+
+impl Display for EvalBreakdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, team) in [("red", Team::Red), ("green", Team::Green)] {
+            let team_eval = self.team(team);
+            writeln!(
+                f,
+                "{:<6}octis {} position {} simple {} jump {} total {}",
+                name,
+                team_eval.octis,
+                team_eval.position,
+                team_eval.simple_moves,
+                team_eval.jump_moves,
+                team_eval.total()
+            )?;
+            writeln!(
+                f,
+                "{:<6}arrows {}",
+                "",
+                team_eval
+                    .arrows
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )?;
+        }
+        match self.winner {
+            Some(_) => write!(f, "eval {}, the terms don't count", self.value()),
+            None => write!(f, "eval {}", self.value()),
+        }
+    }
+}
+
+// the linear matrices evaluator, the default one used by the ai
+impl Evaluator for EvalData {
+    fn evaluate(&self, board: &Board) -> Value {
+        board_eval(board, self)
+    }
+
+    fn symmetries(&self) -> Vec<Symmetry> {
+        Symmetry::ALL
+            .into_iter()
+            .filter(|x| self.is_symmetric(*x))
+            .collect()
+    }
+}
+
 impl EvalData {
-    pub fn default() -> Result<EvalData, Box<dyn Error>> {
-        let reader = BufReader::new(File::open("./src/ai/data/default_eval_data.json")?);
-        Ok(serde_json::from_reader(reader)?)
+    pub fn term_value(&self, team: Team, term: Term) -> i32 {
+        let team_index = team_index(team);
+        match term {
+            Term::Octi => self.octi_value,
+            Term::Arrow(i) => self.arrow_values[team_index][i],
+            Term::Position(pos) => *self.position_matricies[team_index].get(&pos).unwrap(),
+            Term::SimpleMove(pos) => *self.simple_move_matricies[team_index].get(&pos).unwrap(),
+            Term::JumpMove(pos) => *self.jump_move_matricies[team_index].get(&pos).unwrap(),
+        }
+    }
+
+    // whether every term of both teams is worth as much as the term the symmetry maps it to
+    pub fn is_symmetric(&self, symmetry: Symmetry) -> bool {
+        let positions = (0..BOARD_HEIGHT)
+            .flat_map(|y| (0..BOARD_WIDTH).map(move |x| Position::new(x as i32, y as i32)));
+        let mut terms = iter::once(Term::Octi)
+            .chain((0..ARROWS_PER_OCTI).map(Term::Arrow))
+            .chain(positions.flat_map(|pos| {
+                [
+                    Term::Position(pos),
+                    Term::SimpleMove(pos),
+                    Term::JumpMove(pos),
+                ]
+            }));
+
+        terms.all(|term| {
+            [Team::Red, Team::Green].into_iter().all(|team| {
+                self.term_value(team, term)
+                    == self.term_value(symmetry.map_team(team), term.transform(symmetry))
+            })
+        })
+    }
+
+    // every value of the eval data flattened, in the order term_index maps terms to
+    pub fn parameters(&self) -> Vec<i32> {
+        let mut parameters = vec![self.octi_value];
+        for arrow_values in &self.arrow_values {
+            parameters.extend(arrow_values);
+        }
+        for matricies in [
+            &self.position_matricies,
+            &self.simple_move_matricies,
+            &self.jump_move_matricies,
+        ] {
+            for matrix in matricies {
+                parameters.extend(matrix.iter());
+            }
+        }
+        parameters
+    }
+
+    pub fn with_parameters(&self, parameters: &[i32]) -> EvalData {
+        let mut eval_data = self.clone();
+        let mut parameters = parameters.iter().copied();
+
+        eval_data.octi_value = parameters.next().unwrap();
+        for arrow_values in eval_data.arrow_values.iter_mut() {
+            for value in arrow_values.iter_mut() {
+                *value = parameters.next().unwrap();
+            }
+        }
+        for matricies in [
+            &mut eval_data.position_matricies,
+            &mut eval_data.simple_move_matricies,
+            &mut eval_data.jump_move_matricies,
+        ] {
+            for matrix in matricies.iter_mut() {
+                for value in matrix.iter_mut() {
+                    *value = parameters.next().unwrap();
+                }
+            }
+        }
+        eval_data
+    }
+
+    // index of a term in parameters()
+    pub fn term_index(&self, team: Team, term: Term) -> usize {
+        let team_index = team_index(team);
+        let arrows_start = 1;
+        let matricies_start = arrows_start + TEAMS * ARROWS_PER_OCTI;
+        let matrix_size = BOARD_WIDTH * BOARD_HEIGHT;
+        let matrix_index = |kind: usize, pos: Position| {
+            matricies_start
+                + (kind * TEAMS + team_index) * matrix_size
+                + pos.x() as usize
+                + pos.y() as usize * BOARD_WIDTH
+        };
+
+        match term {
+            Term::Octi => 0,
+            Term::Arrow(i) => arrows_start + team_index * ARROWS_PER_OCTI + i,
+            Term::Position(pos) => matrix_index(0, pos),
+            Term::SimpleMove(pos) => matrix_index(1, pos),
+            Term::JumpMove(pos) => matrix_index(2, pos),
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<EvalData, Box<dyn Error>> {
+        let path = path.as_ref();
+        let reader = BufReader::new(
+            File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?,
+        );
+        let eval_data: EvalData = serde_json::from_reader(reader)
+            .map_err(|e| format!("Invalid eval data in {}: {}", path.display(), e))?;
+        eval_data
+            .validate()
+            .map_err(|e| format!("Invalid eval data in {}: {}", path.display(), e))?;
+        Ok(eval_data)
+    }
+
+    // serde bypasses Matrix::new, so the matrix dimensions are checked here against the board
+    pub fn validate(&self) -> Result<(), String> {
+        let matricies = [
+            ("position_matricies", &self.position_matricies),
+            ("simple_move_matricies", &self.simple_move_matricies),
+            ("jump_move_matricies", &self.jump_move_matricies),
+        ];
+
+        for (name, team_matricies) in matricies {
+            for (i, matrix) in team_matricies.iter().enumerate() {
+                if matrix.width() != BOARD_WIDTH || matrix.height() != BOARD_HEIGHT {
+                    Err(format!(
+                        "{}[{}] is {}x{}, expected {}x{}",
+                        name,
+                        i,
+                        matrix.width(),
+                        matrix.height(),
+                        BOARD_WIDTH,
+                        BOARD_HEIGHT
+                    ))?;
+                }
+                if matrix.arr().len() != matrix.width() * matrix.height() {
+                    Err(format!(
+                        "{}[{}] has {} values, expected {}",
+                        name,
+                        i,
+                        matrix.arr().len(),
+                        matrix.width() * matrix.height()
+                    ))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for EvalData {
+    fn default() -> Self {
+        serde_json::from_str(DEFAULT_EVAL_DATA).expect("embedded eval data is valid")
     }
 }
@@ -0,0 +1,291 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use super::super::board::{BoardEventProcessor, Boardable, OctiMove, Team};
+
+use super::board::{Board, UndoStack};
+use super::eval::{Evaluator, Value};
+use super::moveiter::new_octi_move_iterator;
+use super::priority::MoveOrderer;
+use super::{winner, BoardScore, MinimaxResult};
+
+// win rates are reported as a score in [-SCORE_SCALE, SCORE_SCALE] from red's perspective
+const SCORE_SCALE: f64 = 10000.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Playout {
+    Random,
+    Priority,
+}
+
+#[derive(Clone)]
+pub struct MctsConfig {
+    pub iterations: u32,
+    // UCT exploration constant (sqrt(2) is the textbook value for rewards in [0, 1])
+    pub exploration: f64,
+    pub playout: Playout,
+    // playouts are cut after this many plies
+    pub playout_depth: u32,
+    // evaluate the position a playout was cut at instead of calling it a draw
+    pub leaf_eval: bool,
+    // evaluator scores are squashed into a win probability with 1 / (1 + e^(-score / eval_scale))
+    pub eval_scale: f64,
+    pub seed: Option<u64>,
+}
+
+struct Node {
+    board: Board,
+    octi_move: Option<OctiMove>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<OctiMove>,
+    visits: u32,
+    // sum of rewards from the perspective of the team that made the move leading to this node
+    reward: f64,
+}
+
+pub fn mcts<E: Evaluator, O: MoveOrderer>(
+    board: &Board,
+    config: &MctsConfig,
+    evaluator: &E,
+    orderer: &O,
+) -> MinimaxResult {
+    let mut rng = match config.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let mut tree = vec![Node::new(*board, None, None)];
+
+    for _ in 0..config.iterations {
+        // selection
+        let mut index = 0;
+        while tree[index].untried.is_empty() && !tree[index].children.is_empty() {
+            index = select_child(&tree, index, config.exploration);
+        }
+
+        // expansion
+        if !tree[index].untried.is_empty() {
+            let i = rng.gen_range(0..tree[index].untried.len());
+            let octi_move = tree[index].untried.swap_remove(i);
+            let mut next_board = tree[index].board;
+            next_board.make_move(&octi_move).unwrap();
+
+            tree.push(Node::new(next_board, Some(octi_move), Some(index)));
+            let child = tree.len() - 1;
+            tree[index].children.push(child);
+            index = child;
+        }
+
+        // simulation
+        let red_reward = playout(&tree[index].board, config, evaluator, orderer, &mut rng);
+
+        // backpropagation
+        let mut cur = Some(index);
+        while let Some(i) = cur {
+            let node = &mut tree[i];
+            node.visits += 1;
+            node.reward += match node.board.turn() {
+                // red is to move, so green made the move leading here
+                Team::Red => 1.0 - red_reward,
+                Team::Green => red_reward,
+            };
+            cur = node.parent;
+        }
+    }
+
+    let best = tree[0]
+        .children
+        .iter()
+        .copied()
+        .max_by_key(|&i| tree[i].visits);
+
+    match best {
+        Some(best) => {
+            let node = &tree[best];
+            let mover = tree[0].board.turn();
+            let value = match winner(&node.board) {
+                Some(team) => Value::Win(team),
+                None => {
+                    let mover_rate = node.reward / node.visits as f64;
+                    let red_rate = match mover {
+                        Team::Red => mover_rate,
+                        Team::Green => 1.0 - mover_rate,
+                    };
+                    Value::Score(((red_rate - 0.5) * 2.0 * SCORE_SCALE) as i32)
+                }
+            };
+            MinimaxResult(
+                BoardScore(value, principal_depth(&tree, best)),
+                node.octi_move.clone(),
+            )
+        }
+        // the game is already over, or the side to move has no legal moves and has lost
+        None => MinimaxResult(
+            BoardScore(
+                Value::Win(winner(board).unwrap_or(match board.turn() {
+                    Team::Red => Team::Green,
+                    Team::Green => Team::Red,
+                })),
+                0,
+            ),
+            None,
+        ),
+    }
+}
+
+// returns red's reward in [0, 1]
+fn playout<E: Evaluator, O: MoveOrderer>(
+    board: &Board,
+    config: &MctsConfig,
+    evaluator: &E,
+    orderer: &O,
+    rng: &mut StdRng,
+) -> f64 {
+    let mut board = *board;
+    let mut undo = UndoStack::new();
+
+    for _ in 0..config.playout_depth {
+        if let Some(team) = winner(&board) {
+            return team_reward(team);
+        }
+
+        let octi_moves: Vec<OctiMove> = new_octi_move_iterator(&board).collect();
+        if octi_moves.is_empty() {
+            return 1.0 - team_reward(board.turn());
+        }
+
+        let octi_move = match config.playout {
+            Playout::Random => octi_moves.choose(rng).unwrap().clone(),
+            Playout::Priority => {
+                let contexts = orderer.order(&mut board, &mut undo, octi_moves);
+                // a winning move is always played, otherwise moves are sampled weighted by priority
+                if contexts[0].priority() == u32::MAX {
+                    contexts[0].octi_move().clone()
+                } else {
+                    contexts
+                        .choose_weighted(rng, |context| context.priority() as f64 + 1.0)
+                        .unwrap()
+                        .octi_move()
+                        .clone()
+                }
+            }
+        };
+        board.do_move(&octi_move, &mut undo);
+    }
+
+    if let Some(team) = winner(&board) {
+        return team_reward(team);
+    }
+
+    if config.leaf_eval {
+        match evaluator.evaluate(&board) {
+            Value::Win(team) => team_reward(team),
+            Value::Score(score) => 1.0 / (1.0 + (-score as f64 / config.eval_scale).exp()),
+        }
+    } else {
+        0.5
+    }
+}
+
+fn select_child(tree: &[Node], index: usize, exploration: f64) -> usize {
+    let parent_visits = (tree[index].visits as f64).ln();
+
+    *tree[index]
+        .children
+        .iter()
+        .max_by(|&&a, &&b| {
+            let a = uct(&tree[a], parent_visits, exploration);
+            let b = uct(&tree[b], parent_visits, exploration);
+            a.partial_cmp(&b).unwrap()
+        })
+        .unwrap()
+}
+
+fn uct(node: &Node, ln_parent_visits: f64, exploration: f64) -> f64 {
+    let visits = node.visits as f64;
+    node.reward / visits + exploration * (ln_parent_visits / visits).sqrt()
+}
+
+fn principal_depth(tree: &[Node], index: usize) -> u32 {
+    let mut depth = 1;
+    let mut index = index;
+    while let Some(&child) = tree[index].children.iter().max_by_key(|&&i| tree[i].visits) {
+        index = child;
+        depth += 1;
+    }
+    depth
+}
+
+fn team_reward(team: Team) -> f64 {
+    match team {
+        Team::Red => 1.0,
+        Team::Green => 0.0,
+    }
+}
+
+impl Node {
+    fn new(board: Board, octi_move: Option<OctiMove>, parent: Option<usize>) -> Node {
+        // terminal nodes have nothing to expand
+        let untried = if winner(&board).is_some() {
+            vec![]
+        } else {
+            new_octi_move_iterator(&board).collect()
+        };
+
+        Node {
+            board,
+            octi_move,
+            parent,
+            children: vec![],
+            untried,
+            visits: 0,
+            reward: 0.0,
+        }
+    }
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig {
+            iterations: 1000,
+            exploration: std::f64::consts::SQRT_2,
+            playout: Playout::Priority,
+            playout_depth: 20,
+            leaf_eval: true,
+            eval_scale: 1000.0,
+            seed: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::board;
+    use super::super::eval::EvalData;
+    use super::super::priority::PriorityEvalData;
+    use super::*;
+
+    #[test]
+    fn decided_root_reports_the_winner() {
+        // the side to move already stands on the other home
+        for (position, team) in [
+            ("6/1GR3/6/6/6/1RRR2/6 r 0 12", Team::Red),
+            ("6/1GGG2/6/6/6/1RRG2/6 g 12 0", Team::Green),
+        ] {
+            let position: board::Board = position.parse().unwrap();
+            let result = mcts(
+                &Board::new(&position),
+                &MctsConfig {
+                    iterations: 10,
+                    seed: Some(0),
+                    ..MctsConfig::default()
+                },
+                &EvalData::default(),
+                &PriorityEvalData::default(),
+            );
+            assert!(matches!(result.score().value(), Value::Win(x) if x == team));
+            assert!(result.octi_move().is_none());
+        }
+    }
+}
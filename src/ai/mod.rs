@@ -1,154 +1,721 @@
 pub mod board;
+pub mod book;
+pub mod eval;
+pub mod mcts;
+pub mod moveiter;
+pub mod network;
+pub mod priority;
+pub mod skill;
+pub mod solve;
+pub mod tablebase;
+pub mod tune;
+pub mod variant;
 
-mod eval;
 mod matrix;
-mod moveiter;
-mod priority;
 
 use std::error::Error;
+use std::cmp::Reverse;
+use std::fmt::Display;
 use std::{cmp::Ordering, collections::HashMap};
 use std::ops::Neg;
+use std::sync::atomic::{self, AtomicBool};
+use std::time::{Duration, Instant};
 
-use super::board::{Boardable, OctiMove, Position, Team};
+use super::board::{BoardBounds, Boardable, OctiMove, Position, Team};
+use super::global::{BOARD_HEIGHT, BOARD_WIDTH};
 
-use board::Board;
-use eval::{board_eval, EvalData, Value};
+use board::{Board, BoardKey, Symmetry, UndoStack};
+use eval::{Evaluator, Value};
+use mcts::{mcts, MctsConfig};
 use moveiter::new_octi_move_iterator;
-use priority::{get_contexts_sorted, PriorityEvalData};
+use priority::{MoveOrderer, OctiMoveContext, Priority};
+use tablebase::{Tablebase, TbValue};
 
-const BOARD_WIDTH: usize = 6;
-const BOARD_HEIGHT: usize = 7;
 const TEAMS: usize = 2;
 const RED_INDEX: usize = 0;
 const GREEN_INDEX: usize = 1;
 
-pub fn minimax(board: &Board, depth: u32) -> Result<MinimaxResult, Box<dyn Error>> {
-    if depth == 0 {
-        Err("Minimax cannot be depth 0")?;
+// the search algorithms the ai can play with, both produce a MinimaxResult
+pub enum Engine {
+    Minimax(MinimaxConfig),
+    Mcts(MctsConfig),
+}
+
+// cuts a search running in the background short, once stop is set or the deadline passes
+pub struct SearchControl<'a> {
+    pub stop: &'a AtomicBool,
+    pub deadline: Option<Instant>,
+}
+
+// pvs, null_move, late_move_reductions and aspiration are experimental, they return the score of
+// the plain search or cut the tree selectively, but none of them has been shown to play better
+pub struct MinimaxConfig {
+    pub depth: u32,
+    // searches the table move first, then the killer moves of the ply, then the moves with the
+    // most cutoffs in the history table, instead of only following the move orderer
+    pub dynamic_ordering: bool,
+    // principal variation search: every move after the first is searched with a null window
+    // first, and again with the full window only if it beats the best move so far
+    pub pvs: bool,
+    // lets the side to move pass before searching its moves, if a shallower search of the pass
+    // still doesn't get the opponent back inside the window the node is cut off
+    pub null_move: bool,
+    // searches quiet moves late in the move order shallower first, and again at full depth only
+    // if they beat the best move so far
+    pub late_move_reductions: bool,
+    // iterative deepening starts every depth with a window around the score of the previous
+    // one, and searches again with the full window if the score falls outside of it
+    pub aspiration: bool,
+}
+
+// killer moves kept per ply
+const KILLERS: usize = 2;
+// plies the search of a null move is shallower than the node it is tried at
+const NULL_MOVE_REDUCTION: u32 = 2;
+// moves searched at full depth before late moves are reduced
+const FULL_DEPTH_MOVES: usize = 8;
+// plies a node needs left before its late moves are reduced, by a ply
+const REDUCTION_LIMIT: u32 = 3;
+// how far the aspiration window reaches on either side of the previous score, the scores of odd
+// and even depths are often this far apart
+const ASPIRATION_WINDOW: i32 = 100;
+// nodes searched between checks of the stop flag and the deadline
+const STOP_CHECK_NODES: u32 = 1024;
+
+pub fn search<E: Evaluator, O: MoveOrderer>(
+    board: &Board,
+    engine: &Engine,
+    evaluator: &E,
+    orderer: &O,
+    tablebase: Option<&Tablebase>,
+) -> Result<MinimaxResult, Box<dyn Error>> {
+    match engine {
+        Engine::Minimax(config) => minimax(board, config, evaluator, orderer, tablebase),
+        Engine::Mcts(config) => {
+            if config.iterations == 0 {
+                Err("MCTS needs at least one iteration")?;
+            }
+
+            Ok(mcts(board, config, evaluator, orderer))
+        }
     }
+}
 
-    let eval_data = EvalData::default()?;
-    let priority_eval_data = PriorityEvalData::default()?;
-    let mut score_table = HashMap::new();
-    let mut nodes_visited = 0;
-    let result = _minimax(
-        board,
+pub fn minimax<E: Evaluator, O: MoveOrderer>(
+    board: &Board,
+    config: &MinimaxConfig,
+    evaluator: &E,
+    orderer: &O,
+    tablebase: Option<&Tablebase>,
+) -> Result<MinimaxResult, Box<dyn Error>> {
+    let mut search = Search::new(config, evaluator, orderer, tablebase)?;
+    let result = search.minimax(
+        &mut board.clone(),
         0,
-        depth,
-        BoardScore(Value::Win(Team::Green), u32::MAX),
-        BoardScore(Value::Win(Team::Red), u32::MAX),
-        &mut score_table,
-        &mut nodes_visited,
-        &eval_data,
-        &priority_eval_data,
+        config.depth,
+        BoardScore(Value::Win(Team::Green), 0),
+        BoardScore(Value::Win(Team::Red), 0),
     );
 
-    // for debugging
-    // println!("{}", nodes_visited);
-
     Ok(result)
 }
 
-fn _minimax(
+// scores every move of the position with a full window search, best moves for the side to move
+// first
+pub fn score_moves<E: Evaluator, O: MoveOrderer>(
     board: &Board,
-    depth: u32,
+    config: &MinimaxConfig,
+    evaluator: &E,
+    orderer: &O,
+    tablebase: Option<&Tablebase>,
+) -> Result<Vec<(OctiMove, BoardScore)>, Box<dyn Error>> {
+    let mut search = Search::new(config, evaluator, orderer, tablebase)?;
+    Ok(search.score_root(board))
+}
+
+// searches depth 1, 2, ... up to the depth of the config, calling info after every depth, until
+// the control stops it, the result is the one of the deepest finished depth
+//
+// depth 1 always finishes, so there is a move to play
+pub fn iterative_deepening<E: Evaluator, O: MoveOrderer, F: FnMut(&SearchInfo)>(
+    board: &Board,
+    config: &MinimaxConfig,
+    evaluator: &E,
+    orderer: &O,
+    tablebase: Option<&Tablebase>,
+    control: &SearchControl,
+    mut info: F,
+) -> Result<MinimaxResult, Box<dyn Error>> {
+    if config.depth == 0 {
+        Err("Minimax cannot be depth 0")?;
+    }
+
+    let start = Instant::now();
+    let mut nodes = 0;
+    let mut best = None;
+
+    for depth in 1..=config.depth {
+        let depth_config = MinimaxConfig { depth, ..*config };
+        let full_window = (
+            BoardScore(Value::Win(Team::Green), 0),
+            BoardScore(Value::Win(Team::Red), 0),
+        );
+        let window = match best.as_ref().map(|x: &MinimaxResult| x.score().value()) {
+            Some(Value::Score(score)) if config.aspiration => (
+                BoardScore(Value::Score(score - ASPIRATION_WINDOW), 0),
+                BoardScore(Value::Score(score + ASPIRATION_WINDOW), 0),
+            ),
+            _ => full_window,
+        };
+
+        let mut search = Search::new(&depth_config, evaluator, orderer, tablebase)?;
+        if depth > 1 {
+            search.control = Some(control);
+        }
+        let mut result = search.minimax(&mut board.clone(), 0, depth, window.0, window.1);
+        nodes += search.nodes_visited as u64;
+
+        // the score is only a bound outside of the window, the table is full of bounds as well
+        let (score, (alpha, beta)) = (result.score(), window);
+        if window != full_window && !search.aborted && (score <= alpha || score >= beta) {
+            search = Search::new(&depth_config, evaluator, orderer, tablebase)?;
+            search.control = Some(control);
+            result = search.minimax(&mut board.clone(), 0, depth, full_window.0, full_window.1);
+            nodes += search.nodes_visited as u64;
+        }
+        if search.aborted {
+            break;
+        }
+
+        info(&SearchInfo {
+            depth,
+            score: result.score(),
+            nodes,
+            elapsed: start.elapsed(),
+            pv: search.line(board, depth),
+        });
+        let decided = matches!(result.score().value(), Value::Win(_));
+        best = Some(result);
+        // a decided position won't change with depth
+        if decided {
+            break;
+        }
+    }
+
+    Ok(best.unwrap())
+}
+
+// the count best moves like score_moves, each with the line expected to follow it
+pub fn multi_pv<E: Evaluator, O: MoveOrderer>(
+    board: &Board,
+    config: &MinimaxConfig,
+    evaluator: &E,
+    orderer: &O,
+    tablebase: Option<&Tablebase>,
+    count: usize,
+) -> Result<Vec<Variation>, Box<dyn Error>> {
+    let mut search = Search::new(config, evaluator, orderer, tablebase)?;
+    let mut undo = UndoStack::new();
+
+    Ok(search
+        .score_root(board)
+        .into_iter()
+        .take(count)
+        .map(|(octi_move, score)| {
+            let mut next_board = *board;
+            next_board.do_move(&octi_move, &mut undo);
+            let mut octi_moves = vec![octi_move];
+            octi_moves.extend(search.line(&next_board, config.depth - 1));
+            Variation(score, octi_moves)
+        })
+        .collect())
+}
+
+// state shared by every node of a single minimax search
+struct Search<'a, E: Evaluator, O: MoveOrderer> {
     target_depth: u32,
-    alpha: BoardScore,
-    beta: BoardScore,
-    score_table: &mut HashMap<Board, BoardScore>,
-    nodes_visited: &mut u32,
-    eval_data: &EvalData,
-    priority_eval_data: &PriorityEvalData,
-) -> MinimaxResult {
-    *nodes_visited += 1;
-    if depth == target_depth || winner(board).is_some() {
-        return MinimaxResult(BoardScore(board_eval(board, eval_data), depth), None);
-    }
-
-    if let Some(board_score) = score_table.get(board) {
-        // if the depth of the saved board is higher, it means it is deeper in the tree and thus
-        // more accurate
-        if board_score.depth() >= depth {
-            return MinimaxResult(board_score.clone(), None);
-        }
-    }
-
-    let (mut alpha, mut beta) = (alpha, beta);
-    let turn = board.turn();
-    let mut value = match turn {
-        Team::Red => BoardScore(Value::Win(Team::Green), u32::MAX),
-        Team::Green => BoardScore(Value::Win(Team::Red), u32::MAX),
-    };
-    let mut value_move = None;
+    dynamic_ordering: bool,
+    pvs: bool,
+    null_move: bool,
+    late_move_reductions: bool,
+    // the ply right after a null move, where another one isn't tried
+    null_move_ply: Option<u32>,
+    // the score and best move found for every searched position, positions the symmetries of the
+    // evaluator relate share an entry
+    score_table: HashMap<BoardKey, TableEntry>,
+    symmetries: Vec<Symmetry>,
+    // moves that caused a cutoff, per ply
+    killers: Vec<[Option<OctiMove>; KILLERS]>,
+    // cutoffs of every move weighted by the remaining depth squared
+    history: HashMap<OctiMove, u32>,
+    // moves are made and taken back on the searched board
+    undo: UndoStack,
+    nodes_visited: u32,
+    // once the control stops the search its result is meaningless
+    control: Option<&'a SearchControl<'a>>,
+    aborted: bool,
+    evaluator: &'a E,
+    orderer: &'a O,
+    tablebase: Option<&'a Tablebase>,
+}
 
-    let all_contexts = get_contexts_sorted(
-        board,
-        new_octi_move_iterator(board).collect(),
-        priority_eval_data,
-    );
+impl<'a, E: Evaluator, O: MoveOrderer> Search<'a, E, O> {
+    fn new(
+        config: &MinimaxConfig,
+        evaluator: &'a E,
+        orderer: &'a O,
+        tablebase: Option<&'a Tablebase>,
+    ) -> Result<Search<'a, E, O>, Box<dyn Error>> {
+        if config.depth == 0 {
+            Err("Minimax cannot be depth 0")?;
+        }
+
+        Ok(Search {
+            target_depth: config.depth,
+            dynamic_ordering: config.dynamic_ordering,
+            pvs: config.pvs,
+            null_move: config.null_move,
+            late_move_reductions: config.late_move_reductions,
+            null_move_ply: None,
+            score_table: HashMap::new(),
+            symmetries: evaluator.symmetries(),
+            killers: vec![[None, None]; config.depth as usize + 1],
+            history: HashMap::new(),
+            undo: UndoStack::new(),
+            nodes_visited: 0,
+            control: None,
+            aborted: false,
+            evaluator,
+            orderer,
+            tablebase,
+        })
+    }
+
+    fn score_root(&mut self, board: &Board) -> Vec<(OctiMove, BoardScore)> {
+        let mut board = *board;
+        let mut undo = UndoStack::new();
 
-    for context in all_contexts {
-        // don't return none in case all moves are absolute worse
-        if value_move.is_none() {
-            value_move = Some(context.clone().octi_move());
+        let octi_moves = new_octi_move_iterator(&board).collect();
+        let mut scores = self
+            .orderer
+            .order(&mut board, &mut undo, octi_moves)
+            .into_iter()
+            .map(|context| {
+                board.do_move(context.octi_move(), &mut undo);
+                let result = self.minimax(
+                    &mut board,
+                    1,
+                    self.target_depth,
+                    BoardScore(Value::Win(Team::Green), 0),
+                    BoardScore(Value::Win(Team::Red), 0),
+                );
+                board.undo_move(&mut undo);
+                (context.octi_move().clone(), result.score())
+            })
+            .collect::<Vec<_>>();
+
+        // stable, so equal scores keep the move ordering
+        match board.turn() {
+            Team::Red => scores.sort_by_key(|x| Reverse(x.1)),
+            Team::Green => scores.sort_by_key(|x| x.1),
         }
-        // destructure prioritized
-        let result = _minimax(
-            context.board(),
-            depth + 1,
-            target_depth,
-            alpha,
-            beta,
-            score_table,
-            nodes_visited,
-            eval_data,
-            priority_eval_data,
-        );
+        scores
+    }
 
-        let eval = result.score();
+    // follows the best moves the score table has from board, for at most length plies
+    fn line(&self, board: &Board, length: u32) -> Vec<OctiMove> {
+        let mut board = *board;
+        let mut undo = UndoStack::new();
+        let mut line = vec![];
 
-        match turn {
-            Team::Red => {
-                if eval > value || eval.same_lower_depth(&value) {
-                    value = eval;
-                    value_move = Some(context.octi_move());
+        while line.len() < length as usize && winner(&board).is_none() {
+            match self.table_get(&board) {
+                Some(TableEntry {
+                    result: MinimaxResult(_, Some(octi_move)),
+                    ..
+                }) => {
+                    board.do_move(&octi_move, &mut undo);
+                    line.push(octi_move);
                 }
-                if value > alpha || value.same_lower_depth(&alpha) {
-                    alpha = value;
-                }
-                if value >= beta {
-                    break;
+                _ => break,
+            }
+        }
+        line
+    }
+
+    // depth is the ply of the node, it is evaluated once depth reaches the horizon, which reductions
+    // bring closer than the target depth
+    fn minimax(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        horizon: u32,
+        alpha: BoardScore,
+        beta: BoardScore,
+    ) -> MinimaxResult {
+        self.nodes_visited += 1;
+        if self.should_abort() {
+            return MinimaxResult(BoardScore(Value::Score(0), depth), None);
+        }
+        if winner(board).is_some() {
+            return MinimaxResult(BoardScore(self.evaluator.evaluate(board), depth), None);
+        }
+
+        // the root still needs a move, tablebase positions below it are exact
+        if let Some(value) = self.tablebase.filter(|_| depth > 0).and_then(|x| x.probe(board)) {
+            let turn = board.turn();
+            let score = match value {
+                TbValue::Win(d) => BoardScore(Value::Win(turn), depth + d as u32),
+                TbValue::Loss(d) => BoardScore(Value::Win(opponent(turn)), depth + d as u32),
+                TbValue::Draw => BoardScore(Value::Score(0), depth),
+            };
+            return MinimaxResult(score, None);
+        }
+
+        if depth >= horizon {
+            return MinimaxResult(self.quiescence(board, depth, alpha, beta), None);
+        }
+
+        let mut table_move = None;
+        if let Some(entry) = self.table_get(board) {
+            // an entry searched at least as deep answers the node, as long as a bound is past the
+            // side of the window it bounds
+            if entry.draft >= horizon - depth {
+                // the score counts plies from the root, so it is moved from the ply of the entry to
+                // this one to keep the distance to a forced win
+                let board_score = entry.result.score();
+                let score = BoardScore(board_score.value(), board_score.depth() - entry.ply + depth);
+                let answers = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                };
+                if answers {
+                    return MinimaxResult(score, None);
                 }
             }
-            Team::Green => {
-                if eval < value || eval.same_lower_depth(&value) {
-                    value = eval;
-                    value_move = Some(context.octi_move());
+            table_move = entry.result.octi_move();
+        }
+
+        if self.null_move {
+            if let Some(score) = self.null_move_cutoff(board, depth, horizon, alpha, beta) {
+                return MinimaxResult(score, None);
+            }
+        }
+
+        let window = (alpha, beta);
+        let (mut alpha, mut beta) = (alpha, beta);
+        let turn = board.turn();
+        // the worst score for the side to move, losing right away
+        let mut value = match turn {
+            Team::Red => BoardScore(Value::Win(Team::Green), 0),
+            Team::Green => BoardScore(Value::Win(Team::Red), 0),
+        };
+        let mut value_move = None;
+
+        let octi_moves = new_octi_move_iterator(board).collect();
+        let mut all_contexts = self.orderer.order(board, &mut self.undo, octi_moves);
+        if self.dynamic_ordering {
+            self.order_dynamic(depth, table_move.as_ref(), &mut all_contexts);
+        }
+
+        for (i, context) in all_contexts.into_iter().enumerate() {
+            if self.aborted {
+                break;
+            }
+            // don't return none in case all moves are absolute worse
+            if value_move.is_none() {
+                value_move = Some(context.octi_move().clone());
+            }
+
+            let reduce = self.reduces(i, depth, horizon, &context);
+            // a null window just past the best score so far, there is none while it is a win
+            let scout_window = match turn {
+                Team::Red => alpha.step(1).map(|x| (alpha, x)),
+                Team::Green => beta.step(-1).map(|x| (x, beta)),
+            }
+            .filter(|_| self.pvs && i > 0);
+
+            board.do_move(context.octi_move(), &mut self.undo);
+            let mut scout = None;
+            if reduce || scout_window.is_some() {
+                let (scout_alpha, scout_beta) = scout_window.unwrap_or((alpha, beta));
+                let scout_horizon = if reduce { horizon - 1 } else { horizon };
+                let result = self.minimax(board, depth + 1, scout_horizon, scout_alpha, scout_beta);
+                // a move that doesn't beat the best one so far doesn't need an exact score
+                let beats = match turn {
+                    Team::Red => result.score() > alpha,
+                    Team::Green => result.score() < beta,
+                };
+                if !beats {
+                    scout = Some(result);
                 }
-                if value < beta || value.same_lower_depth(&beta) {
-                    beta = value;
+            }
+            let result = match scout {
+                Some(result) => result,
+                None => self.minimax(board, depth + 1, horizon, alpha, beta),
+            };
+            board.undo_move(&mut self.undo);
+
+            let eval = result.score();
+
+            match turn {
+                Team::Red => {
+                    if eval > value || eval.same_lower_depth(&value) {
+                        value = eval;
+                        value_move = Some(context.octi_move().clone());
+                    }
+                    if value > alpha || value.same_lower_depth(&alpha) {
+                        alpha = value;
+                    }
+                    if value >= beta {
+                        self.store_cutoff(depth, context.octi_move());
+                        break;
+                    }
                 }
-                if value <= alpha {
-                    break;
+                Team::Green => {
+                    if eval < value || eval.same_lower_depth(&value) {
+                        value = eval;
+                        value_move = Some(context.octi_move().clone());
+                    }
+                    if value < beta || value.same_lower_depth(&beta) {
+                        beta = value;
+                    }
+                    if value <= alpha {
+                        self.store_cutoff(depth, context.octi_move());
+                        break;
+                    }
                 }
             }
         }
+
+        // the side to move is stuck, which loses the game at this ply
+        if value_move.is_none() && !self.aborted {
+            value = BoardScore(Value::Win(opponent(turn)), depth);
+        }
+
+        // a score outside of the window only bounds the value of the position
+        let bound = if value <= window.0 {
+            Bound::Upper
+        } else if value >= window.1 {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        let entry = TableEntry {
+            result: MinimaxResult(value, value_move.clone()),
+            ply: depth,
+            draft: horizon - depth,
+            bound,
+        };
+        self.table_insert(board, entry);
+
+        MinimaxResult(value, value_move)
     }
 
-    score_table.insert(board.clone(), value);
-    score_table.insert(board.horizontal_flip(), value);
-    // not sure yet if this part checks out, need to review later
-    //
-    // let mut opposite_colors = board.vertical_flip();
-    // opposite_colors.switch_colors();
+    // the entry of the position, mapped back from the canonical one
+    fn table_get(&self, board: &Board) -> Option<TableEntry> {
+        let (key, symmetry) = board.canonical_key(&self.symmetries);
+        let entry = self.score_table.get(&key)?;
+        Some(entry.transform(symmetry, board.bounds()))
+    }
+
+    // shallower searches, like reduced ones, don't replace deeper entries
+    fn table_insert(&mut self, board: &Board, entry: TableEntry) {
+        let (key, symmetry) = board.canonical_key(&self.symmetries);
+        if self.score_table.get(&key).is_some_and(|x| x.draft > entry.draft) {
+            return;
+        }
+        // the symmetries are their own inverses
+        self.score_table.insert(key, entry.transform(symmetry, board.bounds()));
+    }
+
+    // whether the move at index i of the move order is searched a ply shallower first, which the
+    // root and the first FULL_DEPTH_MOVES moves never are
+    fn reduces(&self, i: usize, depth: u32, horizon: u32, context: &OctiMoveContext) -> bool {
+        self.late_move_reductions
+            && depth > 0
+            && i >= FULL_DEPTH_MOVES
+            && horizon - depth >= REDUCTION_LIMIT
+            && is_quiet(context)
+    }
+
+    fn should_abort(&mut self) -> bool {
+        if !self.aborted && self.nodes_visited.is_multiple_of(STOP_CHECK_NODES) {
+            self.aborted = self.control.is_some_and(|x| x.stopped());
+        }
+        self.aborted
+    }
+
+    // moves winning right away stay first, then the table move, the killer moves of the ply and
+    // the moves by history, the move orderer breaks ties
+    fn order_dynamic(
+        &self,
+        depth: u32,
+        table_move: Option<&OctiMove>,
+        contexts: &mut [OctiMoveContext],
+    ) {
+        let killers = &self.killers[depth as usize];
+        contexts.sort_by_cached_key(|context| {
+            let octi_move = context.octi_move();
+            if context.priority() == Priority::MAX {
+                (0, Reverse(0))
+            } else if Some(octi_move) == table_move {
+                (1, Reverse(0))
+            } else if let Some(slot) = killers.iter().position(|x| x.as_ref() == Some(octi_move)) {
+                (2, Reverse((KILLERS - slot) as u32))
+            } else {
+                (3, Reverse(self.history.get(octi_move).copied().unwrap_or(0)))
+            }
+        });
+    }
+
+    fn store_cutoff(&mut self, depth: u32, octi_move: &OctiMove) {
+        if !self.dynamic_ordering {
+            return;
+        }
+
+        let killers = &mut self.killers[depth as usize];
+        if killers[0].as_ref() != Some(octi_move) {
+            killers[1] = killers[0].take();
+            killers[0] = Some(octi_move.clone());
+        }
+
+        let remaining = self.target_depth - depth;
+        *self.history.entry(octi_move.clone()).or_insert(0) += remaining * remaining;
+    }
+
+    // passes the turn and searches the position shallower with a null window at the bound of the
+    // side to move, a score still past the bound cuts the node off
     //
-    // score_table.insert(opposite_colors, -value);
-    // score_table.insert(opposite_colors.horizontal_flip(), -value);
+    // passing assumes the side to move has a move at least as good, which is likely with arrows
+    // left to place, so it isn't tried without them, right after another null move, at the root,
+    // when the evaluation doesn't already reach the bound or when the bound is a win
+    fn null_move_cutoff(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        horizon: u32,
+        alpha: BoardScore,
+        beta: BoardScore,
+    ) -> Option<BoardScore> {
+        let turn = board.turn();
+        if depth == 0
+            || self.null_move_ply == Some(depth)
+            || horizon - depth <= NULL_MOVE_REDUCTION
+            || board.get_arr_count(&turn) == Some(0)
+        {
+            return None;
+        }
+
+        let eval = BoardScore(self.evaluator.evaluate(board), depth);
+        let window = match turn {
+            Team::Red if eval >= beta => (beta.step(-1)?, beta),
+            Team::Green if eval <= alpha => (alpha, alpha.step(1)?),
+            _ => return None,
+        };
+
+        board.set_turn(opponent(turn));
+        self.null_move_ply = Some(depth + 1);
+        let result = self.minimax(
+            board,
+            depth + 1,
+            horizon - NULL_MOVE_REDUCTION,
+            window.0,
+            window.1,
+        );
+        self.null_move_ply = None;
+        board.set_turn(turn);
+
+        // wins after a pass aren't wins of the position
+        let score = result.score();
+        match (turn, score.value()) {
+            (_, Value::Win(_)) => None,
+            (Team::Red, _) if score >= beta => Some(score),
+            (Team::Green, _) if score <= alpha => Some(score),
+            _ => None,
+        }
+    }
+
+    // keeps searching past the target depth while the side to move can capture or win right away,
+    // so positions aren't evaluated in the middle of an exchange, the side to move can always
+    // stand pat on the evaluation instead
+    fn quiescence(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        alpha: BoardScore,
+        beta: BoardScore,
+    ) -> BoardScore {
+        self.nodes_visited += 1;
+        let decided = winner(board).is_some();
+        let mut value = match self.evaluator.evaluate(board) {
+            // the evaluation sees the side to move winning with its next move
+            Value::Win(team) if !decided => BoardScore(Value::Win(team), depth + 1),
+            value => BoardScore(value, depth),
+        };
+        if decided {
+            return value;
+        }
+
+        let (mut alpha, mut beta) = (alpha, beta);
+        let turn = board.turn();
+        match turn {
+            Team::Red if value >= beta => return value,
+            Team::Red => alpha = alpha.max(value),
+            Team::Green if value <= alpha => return value,
+            Team::Green => beta = beta.min(value),
+        }
+
+        let opponent_octis = |board: &Board| board.octis().filter(|x| x.team() != turn).count();
+        let octis = opponent_octis(board);
+
+        let octi_moves = new_octi_move_iterator(board).collect::<Vec<_>>();
+        for octi_move in octi_moves {
+            board.do_move(&octi_move, &mut self.undo);
+            if opponent_octis(board) == octis && winner(board) != Some(turn) {
+                board.undo_move(&mut self.undo);
+                continue;
+            }
+
+            let eval = self.quiescence(board, depth + 1, alpha, beta);
+            board.undo_move(&mut self.undo);
+            match turn {
+                Team::Red => {
+                    value = value.max(eval);
+                    alpha = alpha.max(value);
+                    if value >= beta {
+                        break;
+                    }
+                }
+                Team::Green => {
+                    value = value.min(eval);
+                    beta = beta.min(value);
+                    if value <= alpha {
+                        break;
+                    }
+                }
+            }
+        }
+
+        value
+    }
+}
+
+// captures and moves winning right away change the position too much to be reduced
+fn is_quiet(context: &OctiMoveContext) -> bool {
+    let capture = match context.octi_move() {
+        OctiMove::Move(_, arrs) => arrs.iter().any(|(_, is_capture)| *is_capture),
+        OctiMove::Arrow(..) => false,
+    };
+    !capture && context.priority() != Priority::MAX
+}
 
-    MinimaxResult(value, value_move)
+pub fn legal_moves(board: &Board) -> Vec<OctiMove> {
+    new_octi_move_iterator(board).collect()
 }
 
-fn winner(board: &Board) -> Option<Team> {
+pub fn winner(board: &Board) -> Option<Team> {
     let mut saw_red = false;
     let mut saw_green = false;
 
@@ -157,13 +724,13 @@ fn winner(board: &Board) -> Option<Team> {
         let team = octi.team();
         match team {
             Team::Red => {
-                if is_starting_position(pos, Team::Green) {
+                if is_starting_position(pos, Team::Green, board.bounds()) {
                     return Some(Team::Red);
                 }
                 saw_red = true;
             }
             Team::Green => {
-                if is_starting_position(pos, Team::Red) {
+                if is_starting_position(pos, Team::Red, board.bounds()) {
                     return Some(Team::Green);
                 }
                 saw_green = true;
@@ -181,10 +748,19 @@ fn winner(board: &Board) -> Option<Team> {
 }
 
 // helper functions for submodules
-fn is_starting_position(pos: Position, team: Team) -> bool {
+// the second row from the team's end without the edge columns, reduced boards keep the same rule
+fn is_starting_position(pos: Position, team: Team, bounds: BoardBounds) -> bool {
+    let y = match team {
+        Team::Red => bounds.height() - 2,
+        Team::Green => 1,
+    };
+    pos.y() == y && pos.x() > 0 && pos.x() < bounds.width() - 1
+}
+
+fn opponent(team: Team) -> Team {
     match team {
-        Team::Red => pos.y() == 5 && pos.x() > 0 && pos.x() < 5,
-        Team::Green => pos.y() == 1 && pos.x() > 0 && pos.x() < 5,
+        Team::Red => Team::Green,
+        Team::Green => Team::Red,
     }
 }
 
@@ -207,7 +783,9 @@ fn team_index(team: Team) -> usize {
  * it will make the move that results in a lose in more turns
  *
  * if there are two board states in the tree that are a win to the maximizng player,
- * it will still make the move that results in the game ending in more turns, but is still guaranteed a win
+ * it will make the move that wins in the fewest turns, so the win can't slip away
+ *
+ * the depth counts plies from the root of the search, so it also tells how far away a forced win is
  *
  * theoretical edge case is if there is a board evaluation which is exactly the integer max limit or the integer min limit
  * and is not a win to either sides
@@ -220,12 +798,62 @@ fn team_index(team: Team) -> usize {
 #[derive(Clone)]
 pub struct MinimaxResult(BoardScore, Option<OctiMove>);
 
+// a searched position, ply is the ply it was searched at and draft the plies it was searched to
+// below that
+#[derive(Clone)]
+struct TableEntry {
+    result: MinimaxResult,
+    ply: u32,
+    draft: u32,
+    bound: Bound,
+}
+
+// what the score of an entry says about the value of the position, from red's perspective, a
+// search that fails low or high only finds a bound
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+// what a finished depth of iterative deepening found
+pub struct SearchInfo {
+    depth: u32,
+    score: BoardScore,
+    // over every depth so far
+    nodes: u64,
+    elapsed: Duration,
+    pv: Vec<OctiMove>,
+}
+
+// a root move and the moves expected to follow it, with the score of the root move
+#[derive(Clone)]
+pub struct Variation(BoardScore, Vec<OctiMove>);
+
 // first is eval value
 // second is depth
 
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub struct BoardScore(Value, u32);
 
+impl Default for MinimaxConfig {
+    fn default() -> Self {
+        MinimaxConfig {
+            depth: 4,
+            dynamic_ordering: true,
+            // against the plain search, over 200 games at 100 ms a move, pvs scored -7 elo, null
+            // move +33, lmr +21, aspiration -17, null move with lmr +21 and all four -14, all
+            // within the error of about 48 elo, see the match mode to test them again after
+            // changing the search or the evaluation
+            pvs: false,
+            null_move: false,
+            late_move_reductions: false,
+            aspiration: false,
+        }
+    }
+}
+
 impl MinimaxResult {
     pub fn score(&self) -> BoardScore {
         self.0
@@ -234,6 +862,72 @@ impl MinimaxResult {
     pub fn octi_move(self) -> Option<OctiMove> {
         self.1
     }
+
+    // the result of the position the symmetry maps the searched one to
+    fn transform(&self, symmetry: Symmetry, bounds: BoardBounds) -> MinimaxResult {
+        let score = if symmetry.swaps_colours() {
+            -self.0
+        } else {
+            self.0
+        };
+        MinimaxResult(score, self.1.as_ref().map(|x| symmetry.map_move(x, bounds)))
+    }
+}
+
+impl TableEntry {
+    // the entry of the position the symmetry maps the searched one to
+    fn transform(&self, symmetry: Symmetry, bounds: BoardBounds) -> TableEntry {
+        let bound = match self.bound {
+            Bound::Lower if symmetry.swaps_colours() => Bound::Upper,
+            Bound::Upper if symmetry.swaps_colours() => Bound::Lower,
+            bound => bound,
+        };
+        TableEntry {
+            result: self.result.transform(symmetry, bounds),
+            ply: self.ply,
+            draft: self.draft,
+            bound,
+        }
+    }
+}
+
+impl<'a> SearchControl<'a> {
+    pub fn stopped(&self) -> bool {
+        self.stop.load(atomic::Ordering::Relaxed)
+            || self.deadline.is_some_and(|x| Instant::now() >= x)
+    }
+}
+
+impl SearchInfo {
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub fn score(&self) -> BoardScore {
+        self.score
+    }
+
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn pv(&self) -> &[OctiMove] {
+        &self.pv
+    }
+}
+
+impl Variation {
+    pub fn score(&self) -> BoardScore {
+        self.0
+    }
+
+    pub fn octi_moves(&self) -> &[OctiMove] {
+        &self.1
+    }
 }
 
 impl BoardScore {
@@ -245,20 +939,53 @@ impl BoardScore {
         self.1
     }
 
+    // the moves the winner needs to force the win, a move being a ply of the winner
+    pub fn moves_to_win(&self) -> Option<u32> {
+        match self.0 {
+            Value::Win(_) => Some(self.1.div_ceil(2)),
+            Value::Score(_) => None,
+        }
+    }
+
+    // the score delta points higher, None for wins, which a null window can't be put around
+    fn step(&self, delta: i32) -> Option<BoardScore> {
+        match self.0 {
+            Value::Score(score) => Some(BoardScore(Value::Score(score + delta), self.1)),
+            Value::Win(_) => None,
+        }
+    }
+
+    // equal scores reached deeper are preferred, wins are already ordered by depth
     fn same_lower_depth(&self, other: &BoardScore) -> bool {
-        self.0 == other.0 && other.1 < self.1
+        matches!(self.0, Value::Score(_)) && self.0 == other.0 && other.1 < self.1
     }
 }
 
 impl PartialOrd for BoardScore {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.0.partial_cmp(&other.0)
+        Some(self.cmp(other))
     }
 }
 
+// from red's perspective like values, a win is better the sooner it comes and a loss the later
 impl Ord for BoardScore {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.0.cmp(&other.0)
+        self.0.cmp(&other.0).then_with(|| match self.0 {
+            Value::Win(Team::Red) => other.1.cmp(&self.1),
+            Value::Win(Team::Green) => self.1.cmp(&other.1),
+            Value::Score(_) => Ordering::Equal,
+        })
+    }
+}
+
+impl Display for BoardScore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.0, self.moves_to_win()) {
+            (Value::Win(team), Some(0)) => write!(f, "{:?} won", team),
+            (Value::Win(team), Some(1)) => write!(f, "{:?} wins in 1 move", team),
+            (Value::Win(team), Some(moves)) => write!(f, "{:?} wins in {} moves", team, moves),
+            (value, _) => write!(f, "{}", value),
+        }
     }
 }
 
@@ -269,3 +996,305 @@ impl Neg for BoardScore {
         BoardScore(-self.0, self.1)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::board;
+    use super::eval::EvalData;
+    use super::priority::PriorityEvalData;
+    use super::*;
+
+    // the default evaluation without its symmetries, so the search shares no entries
+    struct Unshared(EvalData);
+
+    impl Evaluator for Unshared {
+        fn evaluate(&self, board: &Board) -> Value {
+            self.0.evaluate(board)
+        }
+    }
+
+    const POSITIONS: [(&str, u32); 3] = [
+        ("6/1GGGG1/6/6/6/1RRRR1/6 r 12 12", 4),
+        ("6/1GGG[46]G1/6/6/4R[12]1/1RR1R1/6 g 10 10", 3),
+        (
+            "6/1G[16]G[46]G[06]G[06]1/6/6/4R[1]1/1R[013]R[4]1R[25]1/6 r 5 4",
+            3,
+        ),
+    ];
+
+    fn root_search<E: Evaluator>(
+        search: &mut Search<E, PriorityEvalData>,
+        board: &Board,
+    ) -> BoardScore {
+        search
+            .minimax(
+                &mut board.clone(),
+                0,
+                search.target_depth,
+                BoardScore(Value::Win(Team::Green), 0),
+                BoardScore(Value::Win(Team::Red), 0),
+            )
+            .score()
+    }
+
+    fn quiescence(board: &Board) -> (Value, u32) {
+        let eval_data = EvalData::default();
+        let orderer = PriorityEvalData::default();
+        let mut search =
+            Search::new(&MinimaxConfig::default(), &eval_data, &orderer, None).unwrap();
+        let score = search.quiescence(
+            &mut board.clone(),
+            0,
+            BoardScore(Value::Win(Team::Green), 0),
+            BoardScore(Value::Win(Team::Red), 0),
+        );
+        (score.value(), search.nodes_visited)
+    }
+
+    #[test]
+    fn quiescence_stands_pat_on_quiet_positions() {
+        let board = Board::new(&board::Board::default());
+        let (value, nodes) = quiescence(&board);
+        assert!(value == EvalData::default().evaluate(&board));
+        assert_eq!(nodes, 1);
+    }
+
+    // the evaluation of the position and of the positions after each move
+    fn evaluate_line(position: &str, octi_moves: &[&str]) -> Vec<Value> {
+        let eval_data = EvalData::default();
+        let position: board::Board = position.parse().unwrap();
+        let mut board = Board::new(&position);
+        let mut undo = UndoStack::new();
+
+        let mut values = vec![eval_data.evaluate(&board)];
+        for octi_move in octi_moves {
+            board.do_move(&octi_move.parse().unwrap(), &mut undo);
+            values.push(eval_data.evaluate(&board));
+        }
+        values
+    }
+
+    #[test]
+    fn quiescence_takes_captures() {
+        // red can jump over the green octi in front of it, green has no reply
+        let position = "4G1/6/6/1R[0]G3/6/6/R5 r 0 0";
+        let values = evaluate_line(position, &["mov (1,3) 0x"]);
+        assert!(values[1] > values[0]);
+
+        let (value, _) = quiescence(&Board::new(&position.parse().unwrap()));
+        assert!(value == values[1]);
+    }
+
+    #[test]
+    fn quiescence_follows_exchanges() {
+        // the same capture, but green can jump back over the red octi
+        let position = "4G1/6/6/1R[0]G3/4G[3]1/6/R5 r 0 0";
+        let values = evaluate_line(position, &["mov (1,3) 0x", "mov (4,4) 3x"]);
+        assert!(values[1] > values[0] && values[2] < values[0]);
+
+        // so red stands pat
+        let (value, _) = quiescence(&Board::new(&position.parse().unwrap()));
+        assert!(value == values[0]);
+    }
+
+    #[test]
+    fn stuck_side_loses_at_its_ply() {
+        // red has no arrows left, so any move of green leaves it without a move
+        let position: board::Board = "6/1G[0]4/6/6/6/2R3/6 g 0 1".parse().unwrap();
+        let config = MinimaxConfig {
+            depth: 2,
+            ..MinimaxConfig::default()
+        };
+        let eval_data = EvalData::default();
+        let orderer = PriorityEvalData::default();
+        let result = minimax(&Board::new(&position), &config, &eval_data, &orderer, None).unwrap();
+
+        let score = result.score();
+        assert!(score == BoardScore(Value::Win(Team::Green), 1));
+        assert_eq!(score.to_string(), "Green wins in 1 move");
+    }
+
+    #[test]
+    fn windows_keep_the_score() {
+        let eval_data = EvalData::default();
+        let orderer = PriorityEvalData::default();
+        let stop = AtomicBool::new(false);
+        let control = SearchControl {
+            stop: &stop,
+            deadline: None,
+        };
+
+        // the last position one ply deeper as well, where stale bounds used to change the score
+        let deeper = (POSITIONS[2].0, POSITIONS[2].1 + 1);
+        for (position, depth) in POSITIONS.into_iter().chain([deeper]) {
+            let position: board::Board = position.parse().unwrap();
+            let board = Board::new(&position);
+            let config = MinimaxConfig {
+                depth,
+                ..MinimaxConfig::default()
+            };
+            let mut search = Search::new(&config, &eval_data, &orderer, None).unwrap();
+            let score = root_search(&mut search, &board);
+
+            let pvs_config = MinimaxConfig {
+                pvs: true,
+                ..config
+            };
+            let mut pvs_search = Search::new(&pvs_config, &eval_data, &orderer, None).unwrap();
+            let pvs_score = root_search(&mut pvs_search, &board);
+            assert!(
+                pvs_score.cmp(&score) == Ordering::Equal,
+                "{} != {}",
+                pvs_score,
+                score
+            );
+
+            let aspiration_config = MinimaxConfig {
+                aspiration: true,
+                ..config
+            };
+            let aspiration_score = iterative_deepening(
+                &board,
+                &aspiration_config,
+                &eval_data,
+                &orderer,
+                None,
+                &control,
+                |_| {},
+            )
+            .unwrap()
+            .score();
+            assert!(
+                aspiration_score.cmp(&score) == Ordering::Equal,
+                "{} != {}",
+                aspiration_score,
+                score
+            );
+        }
+    }
+
+    #[test]
+    fn null_move_needs_a_ply_and_arrows() {
+        let eval_data = EvalData::default();
+        let orderer = PriorityEvalData::default();
+        let config = MinimaxConfig {
+            null_move: true,
+            ..MinimaxConfig::default()
+        };
+        // red is past the bound whatever it plays, so passing would cut off
+        let alpha = BoardScore(Value::Win(Team::Green), 0);
+        let beta = BoardScore(Value::Score(-10000), 0);
+        let horizon = config.depth;
+
+        let position: board::Board = POSITIONS[0].0.parse().unwrap();
+        let mut board = Board::new(&position);
+        let mut search = Search::new(&config, &eval_data, &orderer, None).unwrap();
+        assert!(search
+            .null_move_cutoff(&mut board, 1, horizon, alpha, beta)
+            .is_some());
+        assert!(search
+            .null_move_cutoff(&mut board, 0, horizon, alpha, beta)
+            .is_none());
+
+        // the same position with every arrow of red already placed
+        let position: board::Board = "6/1GGGG1/6/6/6/1R[2]R[2]R[2]R[2]1/6 r 0 12"
+            .parse()
+            .unwrap();
+        let mut board = Board::new(&position);
+        assert!(search
+            .null_move_cutoff(&mut board, 1, horizon, alpha, beta)
+            .is_none());
+    }
+
+    #[test]
+    fn late_moves_are_reduced() {
+        let eval_data = EvalData::default();
+        let orderer = PriorityEvalData::default();
+        let config = MinimaxConfig {
+            late_move_reductions: true,
+            ..MinimaxConfig::default()
+        };
+        let search = Search::new(&config, &eval_data, &orderer, None).unwrap();
+        let horizon = config.depth;
+
+        let position: board::Board = POSITIONS[0].0.parse().unwrap();
+        let mut board = Board::new(&position);
+        let octi_moves = legal_moves(&board);
+        let contexts = orderer.order(&mut board, &mut UndoStack::new(), octi_moves);
+        assert!(contexts.len() > FULL_DEPTH_MOVES);
+
+        for (i, context) in contexts.iter().enumerate() {
+            assert!(!search.reduces(i, 0, horizon, context));
+            let reduces = search.reduces(i, 1, horizon, context);
+            assert_eq!(reduces, i >= FULL_DEPTH_MOVES && is_quiet(context));
+        }
+    }
+
+    #[test]
+    fn shared_entries_keep_scores() {
+        let eval_data = EvalData::default();
+        let unshared = Unshared(EvalData::default());
+        let orderer = PriorityEvalData::default();
+
+        for (position, depth) in POSITIONS {
+            let position: board::Board = position.parse().unwrap();
+            let board = Board::new(&position);
+            for pvs in [false, true] {
+                let config = MinimaxConfig {
+                    depth,
+                    pvs,
+                    ..MinimaxConfig::default()
+                };
+                let mut search = Search::new(&config, &eval_data, &orderer, None).unwrap();
+                let score = root_search(&mut search, &board);
+                let mut unshared_search = Search::new(&config, &unshared, &orderer, None).unwrap();
+                let unshared_score = root_search(&mut unshared_search, &board);
+                assert!(score == unshared_score, "{} != {}", score, unshared_score);
+            }
+        }
+    }
+
+    #[test]
+    fn table_hits_through_symmetries() {
+        let eval_data = EvalData::default();
+        assert!(eval_data.symmetries().len() == Symmetry::ALL.len());
+        let unshared = Unshared(EvalData::default());
+        let orderer = PriorityEvalData::default();
+
+        for (position, depth) in POSITIONS {
+            let position: board::Board = position.parse().unwrap();
+            let board = Board::new(&position);
+            let config = MinimaxConfig {
+                depth,
+                ..MinimaxConfig::default()
+            };
+
+            for symmetry in Symmetry::ALL {
+                let mut search = Search::new(&config, &eval_data, &orderer, None).unwrap();
+                let score = root_search(&mut search, &board);
+
+                // the mapped position is answered from the entry of the original one
+                let mapped = board.transform(symmetry);
+                let nodes = search.nodes_visited;
+                let mapped_score = root_search(&mut search, &mapped);
+                assert_eq!(search.nodes_visited, nodes + 1);
+                let expected = if symmetry.swaps_colours() {
+                    -score
+                } else {
+                    score
+                };
+                assert!(mapped_score == expected, "{} != {}", mapped_score, expected);
+
+                let mut unshared_search = Search::new(&config, &unshared, &orderer, None).unwrap();
+                let unshared_score = root_search(&mut unshared_search, &mapped);
+                assert!(
+                    mapped_score == unshared_score,
+                    "{:?}: {} != {}",
+                    symmetry,
+                    mapped_score,
+                    unshared_score
+                );
+            }
+        }
+    }
+}
@@ -1,8 +1,14 @@
-use super::super::board::{Arrow, BoardEventProcessor, Boardable, OctiMove, Position};
+use super::super::board::{Arrow, BoardEventProcessor, Boardable, Octi, OctiMove, Position};
 use super::board::Board;
+use super::{BOARD_HEIGHT, BOARD_WIDTH};
 
 use super::super::global::ARROWS_PER_OCTI;
 
+// every jump goes over a different square, so no chain is longer than the board has squares
+const MAX_CHAIN: usize = BOARD_WIDTH * BOARD_HEIGHT;
+// at every step of a chain each arrow is tried with and without capture
+const CANDIDATES: u8 = 2 * ARROWS_PER_OCTI as u8;
+
 pub struct OctiMoveIterator<'a, T: Iterator<Item = Position>, U: Iterator<Item = Position>> {
     arr_octi_move_iterator: ArrowOctiMoveIterator<'a, T>,
     move_octi_move_iterator: MoveOctiMoveIterator<'a, U>,
@@ -15,7 +21,29 @@ pub struct ArrowOctiMoveIterator<'a, T: Iterator<Item = Position>> {
     check_stack: Vec<Arrow>,
 }
 
+// walks the move chains of every octi depth first, extending the current chain from its landing
+// square instead of validating every chain from scratch, only the yielded moves allocate
 pub struct MoveOctiMoveIterator<'a, T: Iterator<Item = Position>> {
+    board: &'a Board,
+    octi: Option<Octi>,
+    positions: T,
+    chain: [ChainStep; MAX_CHAIN],
+    len: usize,
+    // the next candidate to try at every step of the chain
+    candidates: [u8; MAX_CHAIN + 1],
+    // squares jumped over by the chain, indexed like the board
+    jumped: u64,
+}
+
+#[derive(Clone, Copy)]
+struct ChainStep {
+    arr: Arrow,
+    capture: bool,
+    pos: Position,
+}
+
+// the chain based iterator the incremental one replaced, kept to check and time it against
+pub struct LegacyMoveOctiMoveIterator<'a, T: Iterator<Item = Position>> {
     board: &'a Board,
     pos: Position,
     positions: T,
@@ -52,6 +80,29 @@ pub fn new_move_octi_move_iterator(
     let turn = board.turn();
 
     MoveOctiMoveIterator {
+        board,
+        octi: None,
+        positions: board
+            .octis()
+            .filter(move |x| x.team() == turn)
+            .map(|x| x.pos()),
+        chain: [ChainStep {
+            arr: Arrow::new(0).unwrap(),
+            capture: false,
+            pos: Position::default(),
+        }; MAX_CHAIN],
+        len: 0,
+        candidates: [0; MAX_CHAIN + 1],
+        jumped: 0,
+    }
+}
+
+pub fn new_legacy_move_octi_move_iterator(
+    board: &Board,
+) -> LegacyMoveOctiMoveIterator<'_, impl Iterator<Item = Position> + '_> {
+    let turn = board.turn();
+
+    LegacyMoveOctiMoveIterator {
         board,
         pos: Position::default(),
         positions: board
@@ -95,9 +146,104 @@ impl<'a, T: Iterator<Item = Position>> Iterator for ArrowOctiMoveIterator<'a, T>
     }
 }
 
+// yields the same moves in the same order as the legacy iterator: arrows are tried from the last
+// to the first, with capture before without, and a chain is yielded before its extensions
 impl<'a, T: Iterator<Item = Position>> Iterator for MoveOctiMoveIterator<'a, T> {
     type Item = OctiMove;
 
+    fn next(&mut self) -> Option<OctiMove> {
+        loop {
+            let octi = match self.octi {
+                Some(octi) => octi,
+                None => {
+                    let pos = self.positions.next()?;
+                    let octi = *self.board.get_octi_by_pos(&pos).unwrap();
+                    self.octi = Some(octi);
+                    self.len = 0;
+                    self.candidates[0] = 0;
+                    self.jumped = 0;
+                    octi
+                }
+            };
+
+            let step = self.len;
+            if self.candidates[step] == CANDIDATES {
+                if step == 0 {
+                    self.octi = None;
+                } else {
+                    self.len -= 1;
+                    let from = self.from(self.len);
+                    let over = from + self.chain[self.len].arr.direction();
+                    self.jumped &= !square_bit(&over);
+                }
+                continue;
+            }
+
+            let candidate = self.candidates[step];
+            self.candidates[step] += 1;
+            let arr = Arrow::new(ARROWS_PER_OCTI - 1 - candidate as usize / 2).unwrap();
+            let capture = candidate.is_multiple_of(2);
+            if !octi.has_arr(&arr) {
+                continue;
+            }
+
+            let from = self.from(step);
+            let direction = arr.direction();
+            let over = from + direction;
+
+            // a single step into an empty square, it can't be extended
+            if step == 0
+                && !capture
+                && self.board.in_bounds(&over)
+                && self.board.get_octi_by_pos(&over).is_none()
+            {
+                return Some(OctiMove::Move(octi.pos(), vec![(arr, false)]));
+            }
+
+            // jumps are checked against the board before the move, captured octis still block
+            // and the octi can jump over or land on its own starting square
+            let pos = from + direction * 2;
+            if !self.board.in_bounds(&pos)
+                || self.jumped & square_bit(&over) != 0
+                || self.board.get_octi_by_pos(&over).is_none()
+                || (self.board.get_octi_by_pos(&pos).is_some() && pos != octi.pos())
+            {
+                continue;
+            }
+
+            self.chain[step] = ChainStep { arr, capture, pos };
+            self.jumped |= square_bit(&over);
+            self.len += 1;
+            self.candidates[self.len] = 0;
+
+            return Some(OctiMove::Move(
+                octi.pos(),
+                self.chain[..self.len]
+                    .iter()
+                    .map(|x| (x.arr, x.capture))
+                    .collect(),
+            ));
+        }
+    }
+}
+
+impl<'a, T: Iterator<Item = Position>> MoveOctiMoveIterator<'a, T> {
+    // where the given step of the chain starts
+    fn from(&self, step: usize) -> Position {
+        match step {
+            0 => self.octi.unwrap().pos(),
+            _ => self.chain[step - 1].pos,
+        }
+    }
+}
+
+fn square_bit(pos: &Position) -> u64 {
+    1 << (pos.x() as usize + pos.y() as usize * BOARD_WIDTH)
+}
+
+impl<'a, T: Iterator<Item = Position>> Iterator for LegacyMoveOctiMoveIterator<'a, T> {
+    type Item = OctiMove;
+
     fn next(&mut self) -> Option<OctiMove> {
         'main: loop {
             if self.check_stack.is_empty() {
@@ -133,3 +279,82 @@ impl<'a, T: Iterator<Item = Position>> Iterator for MoveOctiMoveIterator<'a, T>
         }
     }
 }
+
+// counts the positions depth plies away, with the incremental or the legacy move generator
+pub fn perft(board: &Board, depth: u32, legacy: bool) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let octi_moves: Vec<OctiMove> = if legacy {
+        new_arrow_octi_move_iterator(board)
+            .chain(new_legacy_move_octi_move_iterator(board))
+            .collect()
+    } else {
+        new_octi_move_iterator(board).collect()
+    };
+
+    octi_moves
+        .iter()
+        .map(|octi_move| {
+            let mut next_board = *board;
+            next_board.make_move(octi_move).unwrap();
+            perft(&next_board, depth - 1, legacy)
+        })
+        .sum()
+}
+
+// walks the tree like perft and fails at the first position where both generators differ
+pub fn compare_generators(board: &Board, depth: u32) -> Result<(), String> {
+    if depth == 0 {
+        return Ok(());
+    }
+
+    let octi_moves = new_move_octi_move_iterator(board).collect::<Vec<_>>();
+    let legacy_octi_moves = new_legacy_move_octi_move_iterator(board).collect::<Vec<_>>();
+    if octi_moves != legacy_octi_moves {
+        Err(format!(
+            "Generators differ on a position with {} and {} moves",
+            octi_moves.len(),
+            legacy_octi_moves.len()
+        ))?;
+    }
+
+    for octi_move in new_octi_move_iterator(board) {
+        let mut next_board = *board;
+        next_board.make_move(&octi_move).unwrap();
+        compare_generators(&next_board, depth - 1)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::board;
+    use super::*;
+
+    // the start, positions from engine games and crowded boards where octis with every arrow can
+    // chain jumps over both teams
+    const POSITIONS: [&str; 5] = [
+        "6/1GGGG1/6/6/6/1RRRR1/6 r 12 12",
+        "6/1G[06]G[7]1G[5]1/3G[6]2/6/6/1R[7]R[4]1R[02]R[04]/6 g 6 7",
+        "6/2G[4567]2G[0456]/2G[067]3/5R[12]/2R[0123]3/2R[1234]1R[23]1/6 g 0 1",
+        "6/1G[01234567]G[01234567]G2/1GG1G[01234567]1/1R[01234567]G[01234567]GR[01234567]1/2R[01234567]RR1/1RR1R1/6 r 4 4",
+        "6/1G[01234567]G[01234567]G2/1GG1G[01234567]1/1R[01234567]G[01234567]GR[01234567]1/2R[01234567]RR1/1RR1R1/6 g 4 4",
+    ];
+
+    #[test]
+    fn jump_generator_matches_legacy() {
+        let mut chains = 0;
+        for position in POSITIONS {
+            let position: board::Board = position.parse().unwrap();
+            let board = Board::new(&position);
+            chains += new_move_octi_move_iterator(&board)
+                .filter(|x| matches!(x, OctiMove::Move(_, arrs) if arrs.len() > 1))
+                .count();
+            compare_generators(&board, 2).unwrap();
+        }
+        // the crowded boards are there for the chains
+        assert!(chains > 0);
+    }
+}
@@ -0,0 +1,446 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::super::board::{ArrowStatus, Boardable, Team};
+use super::super::global::ARROWS_PER_OCTI;
+
+use super::board::Board;
+use super::eval::{Evaluator, Value};
+use super::{team_index, winner, BOARD_HEIGHT, BOARD_WIDTH, TEAMS};
+
+const SQUARES: usize = BOARD_WIDTH * BOARD_HEIGHT;
+
+// the input features, every one is 0 unless said otherwise:
+// an octi of a team on a square
+const OCCUPANCY_START: usize = 0;
+// an active arrow of the octi of a team on a square, per direction
+const ARROWS_START: usize = OCCUPANCY_START + TEAMS * SQUARES;
+// the arrows a team has left to place, divided by the arrows of an octi
+const RESERVES_START: usize = ARROWS_START + TEAMS * SQUARES * ARROWS_PER_OCTI;
+// 1 with red to move and -1 with green to move
+const TURN: usize = RESERVES_START + TEAMS;
+const FEATURES: usize = TURN + 1;
+
+// a multilayer perceptron with a single hidden relu layer, the output is the log odds of red
+// winning, which the scale turns into eval points from red's perspective
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Network {
+    hidden: usize,
+    scale: f32,
+    // the weights of a feature are next to each other, indexed by feature * hidden + neuron
+    input_weights: Vec<f32>,
+    hidden_biases: Vec<f32>,
+    output_weights: Vec<f32>,
+    output_bias: f32,
+}
+
+pub struct TrainConfig {
+    pub hidden: usize,
+    pub epochs: u32,
+    pub batch_size: usize,
+    // adam step size
+    pub learning_rate: f64,
+    // eval points per unit of log odds, like the scale tune fits for eval data
+    pub scale: f64,
+    // the weights are initialized and the positions shuffled with it
+    pub seed: u64,
+}
+
+// a position reduced to its nonzero features
+struct Sample {
+    features: Vec<(usize, f32)>,
+    // expected result from red's perspective, 1 is a red win and 0 a green win
+    result: f32,
+}
+
+// calls f with the index and the value of every nonzero feature of the position
+fn features<F: FnMut(usize, f32)>(board: &Board, mut f: F) {
+    for octi in board.octis() {
+        let team = team_index(octi.team());
+        let square = octi.pos().x() as usize + octi.pos().y() as usize * BOARD_WIDTH;
+
+        f(OCCUPANCY_START + team * SQUARES + square, 1.0);
+        for (i, arrow) in octi.arr_iter() {
+            if *arrow == ArrowStatus::Active {
+                f(
+                    ARROWS_START + (team * SQUARES + square) * ARROWS_PER_OCTI + i,
+                    1.0,
+                );
+            }
+        }
+    }
+
+    for team in [Team::Red, Team::Green] {
+        let reserve = board.get_arr_count(&team).unwrap();
+        if reserve > 0 {
+            f(
+                RESERVES_START + team_index(team),
+                reserve as f32 / ARROWS_PER_OCTI as f32,
+            );
+        }
+    }
+
+    f(
+        TURN,
+        match board.turn() {
+            Team::Red => 1.0,
+            Team::Green => -1.0,
+        },
+    );
+}
+
+// decided positions don't depend on the network, the evaluation is a win for the winner
+impl Evaluator for Network {
+    fn evaluate(&self, board: &Board) -> Value {
+        if let Some(team) = winner(board) {
+            return Value::Win(team);
+        }
+
+        let mut active = Vec::new();
+        features(board, |i, x| active.push((i, x)));
+        Value::Score((self.forward(&active).1 * self.scale).round() as i32)
+    }
+}
+
+impl Network {
+    // small random weights, so the hidden neurons start out different
+    pub fn new<R: Rng>(hidden: usize, scale: f32, rng: &mut R) -> Network {
+        let mut random = |n: usize, range: f32| {
+            (0..n)
+                .map(|_| rng.gen_range(-range..range))
+                .collect::<Vec<_>>()
+        };
+
+        Network {
+            hidden,
+            scale,
+            input_weights: random(FEATURES * hidden, 0.1),
+            hidden_biases: vec![0.0; hidden],
+            output_weights: random(hidden, 1.0 / (hidden as f32).sqrt()),
+            output_bias: 0.0,
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Network, Box<dyn Error>> {
+        let path = path.as_ref();
+        let reader = BufReader::new(
+            File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?,
+        );
+        let network: Network = serde_json::from_reader(reader)
+            .map_err(|e| format!("Invalid network in {}: {}", path.display(), e))?;
+        network
+            .validate()
+            .map_err(|e| format!("Invalid network in {}: {}", path.display(), e))?;
+        Ok(network)
+    }
+
+    // serde doesn't know the layer sizes, so they are checked here against the features
+    pub fn validate(&self) -> Result<(), String> {
+        if self.hidden == 0 {
+            Err("The hidden layer is empty")?;
+        }
+        for (name, len, expected) in [
+            (
+                "input_weights",
+                self.input_weights.len(),
+                FEATURES * self.hidden,
+            ),
+            ("hidden_biases", self.hidden_biases.len(), self.hidden),
+            ("output_weights", self.output_weights.len(), self.hidden),
+        ] {
+            if len != expected {
+                Err(format!(
+                    "{} has {} values instead of {}",
+                    name, len, expected
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    // the hidden activations and the output of the features
+    fn forward(&self, features: &[(usize, f32)]) -> (Vec<f32>, f32) {
+        let mut hidden = self.hidden_biases.clone();
+        for (i, x) in features {
+            let weights = &self.input_weights[i * self.hidden..(i + 1) * self.hidden];
+            for (activation, weight) in hidden.iter_mut().zip(weights) {
+                *activation += x * weight;
+            }
+        }
+        hidden.iter_mut().for_each(|x| *x = x.max(0.0));
+
+        let output = self.output_bias
+            + hidden
+                .iter()
+                .zip(&self.output_weights)
+                .map(|(x, weight)| x * weight)
+                .sum::<f32>();
+        (hidden, output)
+    }
+
+    // adds the gradient of the squared error of the sample to gradient
+    fn backward(&self, sample: &Sample, gradient: &mut Network) {
+        let (hidden, output) = self.forward(&sample.features);
+        let p = sigmoid(output);
+        let d = -2.0 * (sample.result - p) * p * (1.0 - p);
+
+        gradient.output_bias += d;
+        for (weight, x) in gradient.output_weights.iter_mut().zip(&hidden) {
+            *weight += d * x;
+        }
+
+        let hidden_gradient = (0..self.hidden)
+            .map(|j| {
+                if hidden[j] > 0.0 {
+                    d * self.output_weights[j]
+                } else {
+                    0.0
+                }
+            })
+            .collect::<Vec<_>>();
+        for (j, x) in hidden_gradient.iter().enumerate() {
+            gradient.hidden_biases[j] += x;
+        }
+        for (i, x) in &sample.features {
+            let weights = &mut gradient.input_weights[i * self.hidden..(i + 1) * self.hidden];
+            for (weight, hidden_x) in weights.iter_mut().zip(&hidden_gradient) {
+                *weight += x * hidden_x;
+            }
+        }
+    }
+
+    // every weight and bias, in the same order for networks of the same size
+    fn parameters_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+        self.input_weights
+            .iter_mut()
+            .chain(self.hidden_biases.iter_mut())
+            .chain(self.output_weights.iter_mut())
+            .chain(std::iter::once(&mut self.output_bias))
+    }
+
+    fn zeroed(&self) -> Network {
+        let mut network = self.clone();
+        network.parameters_mut().for_each(|x| *x = 0.0);
+        network
+    }
+}
+
+// positions are paired with the result of the game they were taken from, from red's perspective,
+// every tenth position is held out to measure the error on positions the network didn't learn
+//
+// progress gets the epoch with the errors on the learned and the held out positions
+pub fn train<F: FnMut(u32, f64, f64)>(
+    positions: &[(Board, f64)],
+    config: &TrainConfig,
+    mut progress: F,
+) -> Result<Network, String> {
+    if config.hidden == 0 || config.batch_size == 0 {
+        Err("The hidden layer and the batches need a size")?;
+    }
+
+    let mut samples = Vec::new();
+    let mut held_out = Vec::new();
+    for (i, (board, result)) in positions.iter().enumerate() {
+        if let Some(sample) = sample(board, *result) {
+            if i % 10 == 9 {
+                held_out.push(sample);
+            } else {
+                samples.push(sample);
+            }
+        }
+    }
+    if samples.is_empty() {
+        Err("No undecided positions to train on")?;
+    }
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut network = Network::new(config.hidden, config.scale as f32, &mut rng);
+
+    // adam
+    let (beta1, beta2, epsilon) = (0.9f32, 0.999f32, 1e-8f32);
+    let mut m = network.zeroed();
+    let mut v = network.zeroed();
+    let mut step = 0;
+
+    for epoch in 1..=config.epochs {
+        samples.shuffle(&mut rng);
+
+        for batch in samples.chunks(config.batch_size) {
+            let mut gradient = network.zeroed();
+            for sample in batch {
+                network.backward(sample, &mut gradient);
+            }
+
+            step += 1;
+            let n = batch.len() as f32;
+            let rate = config.learning_rate as f32;
+            for (((weight, g), m), v) in network
+                .parameters_mut()
+                .zip(gradient.parameters_mut())
+                .zip(m.parameters_mut())
+                .zip(v.parameters_mut())
+            {
+                let g = *g / n;
+                *m = beta1 * *m + (1.0 - beta1) * g;
+                *v = beta2 * *v + (1.0 - beta2) * g * g;
+                let m_hat = *m / (1.0 - beta1.powi(step));
+                let v_hat = *v / (1.0 - beta2.powi(step));
+                *weight -= rate * m_hat / (v_hat.sqrt() + epsilon);
+            }
+        }
+
+        progress(epoch, error(&network, &samples), error(&network, &held_out));
+    }
+
+    Ok(network)
+}
+
+// decided positions are evaluated as wins whatever the network says, so they are skipped
+fn sample(board: &Board, result: f64) -> Option<Sample> {
+    if winner(board).is_some() {
+        return None;
+    }
+
+    let mut active = Vec::new();
+    features(board, |i, x| active.push((i, x)));
+    Some(Sample {
+        features: active,
+        result: result as f32,
+    })
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+// the mean squared error of the predicted results, 0 without samples
+fn error(network: &Network, samples: &[Sample]) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    samples
+        .iter()
+        .map(|x| (x.result - sigmoid(network.forward(&x.features).1)).powi(2) as f64)
+        .sum::<f64>()
+        / samples.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::board;
+    use super::*;
+
+    // positions where a team is octis ahead, with that team winning, and two even ones
+    const POSITIONS: [(&str, f64); 8] = [
+        ("6/1GGGG1/6/6/6/1RRRR1/6 r 12 12", 0.5),
+        ("6/1GGGG1/6/6/6/1RRRR1/6 g 11 12", 0.5),
+        ("6/1G4/6/6/6/1RRRR1/6 r 12 12", 1.0),
+        ("6/1G2G1/6/6/6/1RRRR1/6 g 12 12", 1.0),
+        ("6/1GG[0]GG1/6/6/6/1RRR2/6 g 12 11", 1.0),
+        ("6/1GGGG1/6/6/6/1R4/6 g 12 12", 0.0),
+        ("6/1GGGG1/6/6/6/2R1R1/6 r 12 12", 0.0),
+        ("6/1GGGG1/6/6/6/2RR[0]R1/6 r 11 12", 0.0),
+    ];
+
+    fn positions() -> Vec<(Board, f64)> {
+        POSITIONS
+            .iter()
+            .map(|(position, result)| {
+                let position: board::Board = position.parse().unwrap();
+                (Board::new(&position), *result)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn backward_matches_finite_differences() {
+        let network = Network::new(4, 100.0, &mut StdRng::seed_from_u64(0));
+        let step = 1e-3;
+
+        for (board, result) in positions() {
+            let sample = sample(&board, result).unwrap();
+            let samples = std::slice::from_ref(&sample);
+            let mut gradient = network.zeroed();
+            network.backward(&sample, &mut gradient);
+
+            for (i, expected) in gradient.parameters_mut().enumerate() {
+                let mut moved = |delta: f32| {
+                    let mut network = network.clone();
+                    *network.parameters_mut().nth(i).unwrap() += delta;
+                    error(&network, samples) as f32
+                };
+                let difference = (moved(step) - moved(-step)) / (2.0 * step);
+                assert!(
+                    (difference - *expected).abs() < 1e-3 + 1e-2 * expected.abs(),
+                    "parameter {}: {} != {}",
+                    i,
+                    difference,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn validate_checks_layer_sizes() {
+        let network = Network::new(4, 100.0, &mut StdRng::seed_from_u64(0));
+        assert!(network.validate().is_ok());
+
+        let mut input_weights = network.clone();
+        input_weights.input_weights.pop();
+        assert!(input_weights.validate().is_err());
+
+        let mut hidden_biases = network.clone();
+        hidden_biases.hidden_biases.push(0.0);
+        assert!(hidden_biases.validate().is_err());
+
+        let mut output_weights = network.clone();
+        output_weights.output_weights.pop();
+        assert!(output_weights.validate().is_err());
+
+        // the other layers still match the hidden size
+        let mut hidden = network.clone();
+        hidden.hidden = 5;
+        assert!(hidden.validate().is_err());
+    }
+
+    #[test]
+    fn train_lowers_the_error() {
+        let positions = positions();
+        let config = TrainConfig {
+            hidden: 4,
+            epochs: 50,
+            batch_size: 4,
+            learning_rate: 0.01,
+            scale: 100.0,
+            seed: 0,
+        };
+
+        let mut errors = vec![];
+        train(&positions, &config, |_, error, _| errors.push(error)).unwrap();
+
+        // the network train starts from
+        let samples = positions
+            .iter()
+            .filter_map(|(board, result)| sample(board, *result))
+            .collect::<Vec<_>>();
+        let untrained = Network::new(4, 100.0, &mut StdRng::seed_from_u64(0));
+        let error_before = error(&untrained, &samples);
+
+        assert_eq!(errors.len(), 50);
+        assert!(
+            errors[49] < error_before / 2.0,
+            "{} -> {}",
+            error_before,
+            errors[49]
+        );
+    }
+}
@@ -2,23 +2,41 @@ use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
+use std::path::Path;
 
-use super::{is_starting_position, winner};
+use super::{is_starting_position, winner, BOARD_HEIGHT, BOARD_WIDTH};
 
-use super::super::board::{BoardEventProcessor, Boardable, OctiMove};
+use super::super::board::{Boardable, OctiMove};
 
-use super::board::Board;
+use super::super::global::ARROWS_PER_OCTI;
+
+use super::board::{Board, UndoStack};
 
 pub type Priority = u32;
 
+const DEFAULT_PRIORITY_EVAL_DATA: &str = include_str!("data/default_priority_eval_data.json");
+const MAX_OCTIS: usize = BOARD_WIDTH * BOARD_HEIGHT;
+
+// orders the moves of a position so the most promising ones are searched first, moves can be
+// tried on the board as long as it is given back as it was
+pub trait MoveOrderer {
+    fn order(
+        &self,
+        board: &mut Board,
+        undo: &mut UndoStack,
+        octi_moves: Vec<OctiMove>,
+    ) -> Vec<OctiMoveContext>;
+}
+
 pub fn get_contexts_sorted(
-    board: &Board,
+    board: &mut Board,
+    undo: &mut UndoStack,
     octi_moves: Vec<OctiMove>,
     priority_eval_data: &PriorityEvalData,
 ) -> Vec<OctiMoveContext> {
     let mut contexts = octi_moves
         .into_iter()
-        .map(|x| priority_eval(board, x, priority_eval_data))
+        .map(|x| priority_eval(board, undo, x, priority_eval_data))
         .collect::<Vec<_>>();
 
     contexts.sort();
@@ -26,17 +44,23 @@ pub fn get_contexts_sorted(
     contexts
 }
 
-// assigns priority to moves (returns board after move in order to only calculate next_octis once)
+// assigns priority to moves
 fn priority_eval(
-    board: &Board,
+    board: &mut Board,
+    undo: &mut UndoStack,
     octi_move: OctiMove,
     priority_eval_data: &PriorityEvalData,
 ) -> OctiMoveContext {
-    let mut next_board = board.clone();
-    next_board.make_move(&octi_move);
+    let turn = board.turn();
+    let enemy_octis_count = |board: &Board| board.octis().filter(|x| x.team() != turn).count() as u32;
+
+    let enemy_octis_count_before = enemy_octis_count(board);
+    board.do_move(&octi_move, undo);
+    let game_winner = winner(board);
+    let enemy_octis_count_after = enemy_octis_count(board);
+    board.undo_move(undo);
 
     let mut priority = u32::MIN;
-    let game_winner = winner(&next_board);
     if game_winner.is_some() {
         let game_winner = game_winner.unwrap();
         if game_winner == board.turn() {
@@ -50,7 +74,7 @@ fn priority_eval(
                 let octi = board.get_octi_by_pos(&pos).unwrap();
                 let team = octi.team();
 
-                if !is_starting_position(pos, team) {
+                if !is_starting_position(pos, team, board.bounds()) {
                     priority += priority_eval_data.has_moved_value;
                 }
 
@@ -64,13 +88,10 @@ fn priority_eval(
                 let octi = board.get_octi_by_pos(&pos).unwrap();
                 let team = octi.team();
 
-                if !is_starting_position(pos, team) {
+                if !is_starting_position(pos, team, board.bounds()) {
                     priority += priority_eval_data.has_moved_value;
                 }
 
-                let enemy_octis_count_before = board.octis().filter(|x| x.team() != team).count() as u32;
-                let enemy_octis_count_after = (&next_board).octis().filter(|x| x.team() != team).count() as u32;
-
                 priority +=
                     (enemy_octis_count_before - enemy_octis_count_after) * priority_eval_data.kill_value;
             }
@@ -79,7 +100,6 @@ fn priority_eval(
 
     OctiMoveContext {
         octi_move,
-        board: next_board,
         priority,
     }
 }
@@ -92,28 +112,73 @@ pub struct PriorityEvalData {
     arrow_value: u32,
 }
 
-// an octi move with context (board state)
+// an octi move with its priority
 #[derive(Clone)]
 pub struct OctiMoveContext {
     octi_move: OctiMove,
-    board: Board,
     priority: Priority,
 }
 
+impl MoveOrderer for PriorityEvalData {
+    fn order(
+        &self,
+        board: &mut Board,
+        undo: &mut UndoStack,
+        octi_moves: Vec<OctiMove>,
+    ) -> Vec<OctiMoveContext> {
+        get_contexts_sorted(board, undo, octi_moves, self)
+    }
+}
+
 impl PriorityEvalData {
-    pub fn default() -> Result<PriorityEvalData, Box<dyn Error>> {
-        let reader = BufReader::new(File::open("./src/ai/data/default_priority_eval_data.json")?);
-        Ok(serde_json::from_reader(reader)?)
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<PriorityEvalData, Box<dyn Error>> {
+        let path = path.as_ref();
+        let reader = BufReader::new(
+            File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?,
+        );
+        let priority_eval_data: PriorityEvalData = serde_json::from_reader(reader)
+            .map_err(|e| format!("Invalid priority eval data in {}: {}", path.display(), e))?;
+        priority_eval_data
+            .validate()
+            .map_err(|e| format!("Invalid priority eval data in {}: {}", path.display(), e))?;
+        Ok(priority_eval_data)
+    }
+
+    // priorities are summed as u32 with u32::MAX reserved for winning moves, so the largest
+    // possible priority of a move has to stay below it
+    pub fn validate(&self) -> Result<(), String> {
+        let max_arrow = self.arrow_value.checked_mul(ARROWS_PER_OCTI as u32);
+        // at most every enemy octi can be eaten in a single chain
+        let max_kill = self.kill_value.checked_mul(MAX_OCTIS as u32);
+        let max_priority = max_arrow
+            .zip(max_kill)
+            .and_then(|(max_arrow, max_kill)| {
+                self.has_moved_value
+                    .checked_add(self.mov_value)?
+                    .checked_add(max_arrow.max(max_kill))
+            });
+
+        match max_priority {
+            Some(max_priority) if max_priority < u32::MAX => Ok(()),
+            _ => Err(String::from("values are too large, move priorities would overflow")),
+        }
+    }
+}
+
+impl Default for PriorityEvalData {
+    fn default() -> Self {
+        serde_json::from_str(DEFAULT_PRIORITY_EVAL_DATA)
+            .expect("embedded priority eval data is valid")
     }
 }
 
 impl OctiMoveContext {
-    pub fn board(&self) -> &Board {
-        &self.board
+    pub fn octi_move(&self) -> &OctiMove {
+        &self.octi_move
     }
 
-    pub fn octi_move(self) -> OctiMove {
-        self.octi_move
+    pub fn priority(&self) -> Priority {
+        self.priority
     }
 }
 
@@ -0,0 +1,230 @@
+use std::error::Error;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::super::board::{Boardable, OctiMove, Team};
+
+use super::board::Board;
+use super::eval::{Evaluator, Value};
+use super::priority::MoveOrderer;
+use super::tablebase::Tablebase;
+use super::{score_moves, BoardScore, MinimaxConfig};
+
+// how strong the ai plays, the levels below full strength search shallower, evaluate with noise
+// and don't always pick the best move
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Skill {
+    Easy,
+    Medium,
+    Hard,
+    // full strength at the given depth
+    Depth(u32),
+}
+
+pub struct SkillConfig {
+    pub depth: u32,
+    // in eval points, every leaf evaluation is moved by up to this much either way
+    pub noise: i32,
+    // the best moves are picked with probability e^(score / temperature), 0 always picks the best
+    pub temperature: f64,
+    // how many of the best moves can be picked
+    pub top: usize,
+}
+
+// the noise of a position is a hash of the position and the seed, so a position keeps its noise
+// across the search and transposition table entries stay consistent, the octi ids are left out
+// like the table leaves them out
+//
+// the hash is fnv-1a, unlike the std hasher it gives the same noise on every rust version and
+// platform, so a seeded game is replayed the same way
+pub struct NoisyEvaluator<'a, E: Evaluator> {
+    evaluator: &'a E,
+    noise: i32,
+    seed: u64,
+}
+
+impl Skill {
+    pub fn config(&self) -> SkillConfig {
+        match self {
+            Skill::Easy => SkillConfig {
+                depth: 1,
+                noise: 100,
+                temperature: 50.0,
+                top: 5,
+            },
+            Skill::Medium => SkillConfig {
+                depth: 2,
+                noise: 30,
+                temperature: 15.0,
+                top: 3,
+            },
+            Skill::Hard => SkillConfig {
+                depth: 3,
+                noise: 5,
+                temperature: 3.0,
+                top: 2,
+            },
+            Skill::Depth(depth) => SkillConfig {
+                depth: *depth,
+                noise: 0,
+                temperature: 0.0,
+                top: 1,
+            },
+        }
+    }
+}
+
+impl FromStr for Skill {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy" => Ok(Skill::Easy),
+            "medium" => Ok(Skill::Medium),
+            "hard" => Ok(Skill::Hard),
+            _ => Ok(Skill::Depth(
+                s.parse().map_err(|_| format!("Invalid skill: {}", s))?,
+            )),
+        }
+    }
+}
+
+impl<'a, E: Evaluator> Evaluator for NoisyEvaluator<'a, E> {
+    fn evaluate(&self, board: &Board) -> Value {
+        match self.evaluator.evaluate(board) {
+            Value::Score(score) if self.noise > 0 => {
+                let mut hasher = FnvHasher(FNV_OFFSET);
+                (board.key(), self.seed).hash(&mut hasher);
+                let noise = (hasher.finish() % (2 * self.noise as u64 + 1)) as i32 - self.noise;
+                Value::Score(score + noise)
+            }
+            value => value,
+        }
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// integers are hashed little endian and sizes as u64, so the hash doesn't depend on the platform
+struct FnvHasher(u64);
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as u64);
+    }
+}
+
+// picks a move at the given skill, None if there are no legal moves
+pub fn pick_move<E: Evaluator, O: MoveOrderer, R: Rng>(
+    board: &Board,
+    skill: &Skill,
+    evaluator: &E,
+    orderer: &O,
+    tablebase: Option<&Tablebase>,
+    rng: &mut R,
+) -> Result<Option<OctiMove>, Box<dyn Error>> {
+    let config = skill.config();
+    let evaluator = NoisyEvaluator {
+        evaluator,
+        noise: config.noise,
+        seed: rng.gen(),
+    };
+    let minimax_config = MinimaxConfig {
+        depth: config.depth,
+        ..MinimaxConfig::default()
+    };
+
+    let scores = score_moves(board, &minimax_config, &evaluator, orderer, tablebase)?;
+    let (best_move, best_score) = match scores.first() {
+        Some(best) => best,
+        None => return Ok(None),
+    };
+
+    let turn = board.turn();
+    let best_value = match mover_score(best_score, turn) {
+        // a won position is never thrown away and a lost one has nothing to choose from
+        None => return Ok(Some(best_move.clone())),
+        Some(score) => score,
+    };
+    if config.temperature <= 0.0 {
+        return Ok(Some(best_move.clone()));
+    }
+
+    // losing moves are left out, the best move is always a candidate
+    let candidates = scores
+        .iter()
+        .take(config.top)
+        .filter_map(|(octi_move, score)| Some((octi_move, mover_score(score, turn)?)))
+        .collect::<Vec<_>>();
+    let (octi_move, _) = candidates.choose_weighted(rng, |(_, score)| {
+        ((score - best_value) as f64 / config.temperature).exp()
+    })?;
+    Ok(Some((*octi_move).clone()))
+}
+
+// the score from the perspective of the side to move, None for decided positions
+fn mover_score(score: &BoardScore, turn: Team) -> Option<i32> {
+    match (score.value(), turn) {
+        (Value::Score(score), Team::Red) => Some(score),
+        (Value::Score(score), Team::Green) => Some(-score),
+        (Value::Win(_), _) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::eval::EvalData;
+    use super::*;
+
+    #[test]
+    fn noise_is_fixed() {
+        // the reference value of fnv-1a for "a"
+        let mut hasher = FnvHasher(FNV_OFFSET);
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+
+        // a seeded game only replays if the noise never changes
+        let board = Board::new(&"6/1GGGG1/6/6/6/1RRRR1/6 r 12 12".parse().unwrap());
+        let eval_data = EvalData::default();
+        let evaluator = NoisyEvaluator {
+            evaluator: &eval_data,
+            noise: 100,
+            seed: 1,
+        };
+        let noise = match (eval_data.evaluate(&board), evaluator.evaluate(&board)) {
+            (Value::Score(score), Value::Score(noisy)) => noisy - score,
+            _ => panic!("the start position isn't decided"),
+        };
+        assert_eq!(noise, 62);
+    }
+}
@@ -0,0 +1,286 @@
+use super::super::board::{Boardable, OctiMove, Team};
+
+use super::board::{Board, UndoStack};
+use super::moveiter::new_octi_move_iterator;
+use super::winner;
+
+// proof and disproof numbers of a decided node
+const INFINITY: u32 = u32::MAX;
+
+pub struct SolveConfig {
+    // the search gives up once the tree has this many nodes
+    pub max_nodes: usize,
+}
+
+// for the side to move of the solved position
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Won,
+    Lost,
+    // the node limit was reached first
+    Unknown,
+}
+
+pub struct Solution {
+    outcome: Outcome,
+    // the winner playing the quickest win of the proof and the loser resisting the longest, empty
+    // if the outcome is unknown
+    line: Vec<OctiMove>,
+    nodes: usize,
+}
+
+// a node of the proof number search tree, the side to move of the root is the one trying to win,
+// so it is an or node at even plies and an and node at odd plies
+struct Node {
+    // None for the root
+    octi_move: Option<OctiMove>,
+    parent: usize,
+    // the children of a node are created together, so they are next to each other
+    first_child: usize,
+    children: usize,
+    expanded: bool,
+    or: bool,
+    // how many leaves still have to be proven won or lost for the root to be
+    proof: u32,
+    disproof: u32,
+}
+
+// proof number search: expands the most proving leaf until the root is proven won or lost for its
+// side to move, or the tree reaches the node limit
+//
+// a side without moves loses, positions repeating on a path are searched like any other, so a
+// position is only proven if the win doesn't rely on the opponent running out of time
+pub fn solve(board: &Board, config: &SolveConfig) -> Solution {
+    if let Some(team) = winner(board) {
+        return Solution {
+            outcome: outcome(team, board.turn()),
+            line: vec![],
+            nodes: 1,
+        };
+    }
+
+    let mut nodes = vec![Node {
+        octi_move: None,
+        parent: 0,
+        first_child: 0,
+        children: 0,
+        expanded: false,
+        or: true,
+        proof: 1,
+        disproof: 1,
+    }];
+    let attacker = board.turn();
+
+    while nodes[0].proof != 0 && nodes[0].disproof != 0 && nodes.len() < config.max_nodes {
+        let mut board = *board;
+        let mut undo = UndoStack::new();
+        let mut index = 0;
+        while nodes[index].expanded {
+            index = most_proving_child(&nodes, index);
+            board.do_move(nodes[index].octi_move.as_ref().unwrap(), &mut undo);
+        }
+
+        expand(&mut nodes, index, &mut board, attacker);
+        update_ancestors(&mut nodes, index);
+    }
+
+    let outcome = if nodes[0].proof == 0 {
+        Outcome::Won
+    } else if nodes[0].disproof == 0 {
+        Outcome::Lost
+    } else {
+        Outcome::Unknown
+    };
+    Solution {
+        outcome,
+        line: line(&nodes, outcome),
+        nodes: nodes.len(),
+    }
+}
+
+fn outcome(winner: Team, turn: Team) -> Outcome {
+    if winner == turn {
+        Outcome::Won
+    } else {
+        Outcome::Lost
+    }
+}
+
+// the child an or node needs the fewest proofs for, or an and node the fewest disproofs
+fn most_proving_child(nodes: &[Node], index: usize) -> usize {
+    let node = &nodes[index];
+    let children = node.first_child..node.first_child + node.children;
+    if node.or {
+        children.min_by_key(|x| nodes[*x].proof).unwrap()
+    } else {
+        children.min_by_key(|x| nodes[*x].disproof).unwrap()
+    }
+}
+
+// adds the children of a leaf, decided children are proven or disproven right away
+fn expand(nodes: &mut Vec<Node>, index: usize, board: &mut Board, attacker: Team) {
+    let octi_moves = new_octi_move_iterator(board).collect::<Vec<_>>();
+    let first_child = nodes.len();
+    let mut undo = UndoStack::new();
+
+    for octi_move in octi_moves {
+        board.do_move(&octi_move, &mut undo);
+        let (proof, disproof) = match winner(board) {
+            Some(team) if team == attacker => (0, INFINITY),
+            Some(_) => (INFINITY, 0),
+            None => (1, 1),
+        };
+        board.undo_move(&mut undo);
+
+        nodes.push(Node {
+            octi_move: Some(octi_move),
+            parent: index,
+            first_child: 0,
+            children: 0,
+            expanded: false,
+            or: !nodes[index].or,
+            proof,
+            disproof,
+        });
+    }
+
+    let children = nodes.len() - first_child;
+    let node = &mut nodes[index];
+    node.first_child = first_child;
+    node.children = children;
+    node.expanded = true;
+}
+
+// recomputes the numbers from the expanded leaf up, until a node doesn't change
+fn update_ancestors(nodes: &mut [Node], mut index: usize) {
+    loop {
+        let node = &nodes[index];
+        let children = &nodes[node.first_child..node.first_child + node.children];
+        let min = |f: fn(&Node) -> u32| children.iter().map(f).min().unwrap_or(INFINITY);
+        let sum = |f: fn(&Node) -> u32| children.iter().fold(0, |acc, x| f(x).saturating_add(acc));
+
+        // without children the side to move has lost
+        let numbers = if node.or {
+            (min(|x| x.proof), sum(|x| x.disproof))
+        } else {
+            (sum(|x| x.proof), min(|x| x.disproof))
+        };
+
+        let node = &mut nodes[index];
+        let changed = numbers != (node.proof, node.disproof);
+        (node.proof, node.disproof) = numbers;
+        if !changed || index == 0 {
+            break;
+        }
+        index = node.parent;
+    }
+}
+
+// follows the proof tree from the root, the winner picks the child decided in the fewest plies and
+// the loser the one decided in the most
+fn line(nodes: &[Node], outcome: Outcome) -> Vec<OctiMove> {
+    let won = match outcome {
+        Outcome::Won => true,
+        Outcome::Lost => false,
+        Outcome::Unknown => return vec![],
+    };
+
+    let mut plies = vec![0; nodes.len()];
+    // children always come after their parent, so they are done first going backwards
+    for index in (0..nodes.len()).rev() {
+        if let Some(child) = decided_child(nodes, &plies, index, won) {
+            plies[index] = plies[child] + 1;
+        }
+    }
+
+    let mut line = vec![];
+    let mut index = 0;
+    while let Some(child) = decided_child(nodes, &plies, index, won) {
+        line.push(nodes[child].octi_move.clone().unwrap());
+        index = child;
+    }
+    line
+}
+
+// the child the line continues with, None for a leaf or a node that isn't decided
+fn decided_child(nodes: &[Node], plies: &[u32], index: usize, won: bool) -> Option<usize> {
+    let node = &nodes[index];
+    let decided = |x: &Node| if won { x.proof == 0 } else { x.disproof == 0 };
+    if !decided(node) {
+        return None;
+    }
+
+    let children =
+        (node.first_child..node.first_child + node.children).filter(|x| decided(&nodes[*x]));
+    // the winner is to move at or nodes of a won root and at and nodes of a lost one
+    if node.or == won {
+        children.min_by_key(|x| plies[*x])
+    } else {
+        children.max_by_key(|x| plies[*x])
+    }
+}
+
+impl Solution {
+    // Getters
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    pub fn line(&self) -> &[OctiMove] {
+        &self.line
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::board;
+    use super::super::eval::{EvalData, Value};
+    use super::super::priority::PriorityEvalData;
+    use super::super::{minimax, MinimaxConfig};
+    use super::*;
+
+    // red walks its octi up to green's home in two moves, green's can only walk away
+    const MATE_IN_2: &str = "6/6/6/2R[2]3/G[2]5/6/6 r 0 0";
+
+    #[test]
+    fn agrees_with_minimax_on_a_mate_in_2() {
+        for (turn, depth, outcome) in [("r", 3, Outcome::Won), ("g", 4, Outcome::Lost)] {
+            let position: board::Board = MATE_IN_2
+                .replace(" r ", &format!(" {} ", turn))
+                .parse()
+                .unwrap();
+            let board = Board::new(&position);
+
+            let solution = solve(&board, &SolveConfig { max_nodes: 10_000 });
+            assert_eq!(solution.outcome(), outcome);
+            assert_eq!(solution.line().len(), depth as usize);
+
+            let result = minimax(
+                &board,
+                &MinimaxConfig {
+                    depth,
+                    ..MinimaxConfig::default()
+                },
+                &EvalData::default(),
+                &PriorityEvalData::default(),
+                None,
+            )
+            .unwrap();
+            assert!(result.score().value() == Value::Win(Team::Red));
+            assert_eq!(result.score().moves_to_win(), Some(2));
+
+            // the line ends with red's octi home
+            let mut board = board;
+            let mut undo = UndoStack::new();
+            for octi_move in solution.line() {
+                board.do_move(octi_move, &mut undo);
+            }
+            assert_eq!(winner(&board), Some(Team::Red));
+        }
+    }
+}
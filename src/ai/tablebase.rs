@@ -0,0 +1,729 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::thread;
+
+use super::super::board::{
+    ArrowStatus, BoardBounds, BoardEventProcessor, Boardable, Octi, OctiMove, Position, Team,
+};
+
+use super::board::Board;
+use super::moveiter::new_octi_move_iterator;
+use super::{team_index, winner, BOARD_HEIGHT, BOARD_WIDTH, TEAMS};
+
+// Endgame tablebases
+//
+// positions are grouped by material: the octi count of every team and the total arrows of every
+// team (arrows on its octis plus its reserve). Without a capture the material of a position never
+// changes, captures lead to material with fewer octis, so every material is solved after the
+// materials with fewer octis it can capture its way into.
+//
+// every position of a material has a dense index (octi squares, arrows of the octis and turn) and
+// is solved by retrograde analysis in plies: a position is won in n plies if a move leads to a
+// position lost in n - 1 plies, and lost in n plies if every move leads to a won position, the
+// longest of them being won in n - 1 plies. Positions never resolved are draws.
+//
+// file format (little endian): "OCTB", version (u8), table count (u32), then for every table its
+// material (red octis, green octis, red arrows, green arrows as u8), its position count (u64)
+// and one u16 per position
+
+const SQUARES: usize = BOARD_WIDTH * BOARD_HEIGHT;
+const MAGIC: &[u8; 4] = b"OCTB";
+const VERSION: u8 = 1;
+// arrow masks of a team's octis are packed 8 bits each into a u64
+const MAX_OCTIS: usize = 8;
+
+// from the perspective of the side to move, distances are in plies until the game ends
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TbValue {
+    Win(u16),
+    Loss(u16),
+    Draw,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, PartialOrd, Ord)]
+pub struct Material {
+    octis: [u8; TEAMS],
+    arrows: [u8; TEAMS],
+}
+
+pub struct Tablebase {
+    tables: HashMap<Material, Table>,
+}
+
+struct Table {
+    indexer: Indexer,
+    values: Vec<Entry>,
+}
+
+struct Indexer {
+    material: Material,
+    // every combination of arrow masks a team's octis (ordered by square) can have
+    arrow_sets: [Vec<u64>; TEAMS],
+    arrow_set_indices: [HashMap<u64, u64>; TEAMS],
+    size: u64,
+}
+
+// draws (and positions not resolved yet) are 0, wins are odd and losses even
+type Entry = u16;
+const DRAW: Entry = 0;
+
+// where a move of a position being solved leads, either a position of the same material or a
+// position whose value is already known
+enum Child {
+    Index(u32),
+    Known(Entry),
+}
+
+impl Material {
+    pub fn new(red_octis: u8, green_octis: u8, red_arrows: u8, green_arrows: u8) -> Material {
+        Material {
+            octis: [red_octis, green_octis],
+            arrows: [red_arrows, green_arrows],
+        }
+    }
+
+    pub fn of(board: &Board) -> Material {
+        let mut octis = [0; TEAMS];
+        let mut arrows = [0u32; TEAMS];
+        for team in [Team::Red, Team::Green] {
+            arrows[team_index(team)] = board.get_arr_count(&team).unwrap();
+        }
+        for octi in board.octis() {
+            octis[team_index(octi.team())] += 1;
+            arrows[team_index(octi.team())] += octi.arr_count();
+        }
+
+        Material {
+            octis,
+            arrows: arrows.map(|x| x.min(u8::MAX as u32) as u8),
+        }
+    }
+
+    fn octi_count(&self) -> usize {
+        self.octis.iter().map(|x| *x as usize).sum()
+    }
+
+    // every material with fewer octis (and the same arrows in total) a position can reach
+    fn submaterials(&self) -> Vec<Material> {
+        let total_arrows = self.arrows[0] as u32 + self.arrows[1] as u32;
+        let mut materials = Vec::new();
+
+        for red in 1..=self.octis[0] {
+            for green in 1..=self.octis[1] {
+                if red == self.octis[0] && green == self.octis[1] {
+                    continue;
+                }
+                for red_arrows in 0..=total_arrows.min(u8::MAX as u32) {
+                    let green_arrows = total_arrows - red_arrows;
+                    if green_arrows <= u8::MAX as u32 {
+                        materials.push(Material::new(
+                            red,
+                            green,
+                            red_arrows as u8,
+                            green_arrows as u8,
+                        ));
+                    }
+                }
+            }
+        }
+
+        materials
+    }
+}
+
+impl Tablebase {
+    pub fn new() -> Tablebase {
+        Tablebase {
+            tables: HashMap::new(),
+        }
+    }
+
+    // solves the material and every material it can capture its way into
+    pub fn generate<F: FnMut(Material, u64)>(
+        material: Material,
+        threads: usize,
+        mut progress: F,
+    ) -> Result<Tablebase, String> {
+        for team in [Team::Red, Team::Green] {
+            let octis = material.octis[team_index(team)] as usize;
+            if octis == 0 || octis > MAX_OCTIS {
+                Err(format!(
+                    "{:?} needs between 1 and {} octis",
+                    team, MAX_OCTIS
+                ))?;
+            }
+        }
+        if material.octi_count() > SQUARES {
+            Err("More octis than squares")?;
+        }
+
+        let mut materials = material.submaterials();
+        materials.push(material);
+        materials.sort_by_key(|x| (x.octi_count(), *x));
+
+        let mut tablebase = Tablebase::new();
+        for material in materials {
+            let indexer = Indexer::new(material);
+            if indexer.size > u32::MAX as u64 {
+                Err(format!(
+                    "{} has too many positions: {}",
+                    material, indexer.size
+                ))?;
+            }
+            progress(material, indexer.size);
+            let values = tablebase.solve(&indexer, threads.max(1));
+            tablebase.tables.insert(material, Table { indexer, values });
+        }
+
+        Ok(tablebase)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Tablebase, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(
+            File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?,
+        );
+        let invalid = |e: &str| format!("Invalid tablebase {}: {}", path.display(), e);
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            Err(invalid("not a tablebase file"))?;
+        }
+        let mut version = [0; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != VERSION {
+            Err(invalid(&format!("unsupported version {}", version[0])))?;
+        }
+
+        let mut count = [0; 4];
+        reader.read_exact(&mut count)?;
+        let mut tablebase = Tablebase::new();
+
+        for _ in 0..u32::from_le_bytes(count) {
+            let mut material = [0; 4];
+            reader.read_exact(&mut material)?;
+            let material = Material::new(material[0], material[1], material[2], material[3]);
+
+            let mut size = [0; 8];
+            reader.read_exact(&mut size)?;
+            let size = u64::from_le_bytes(size);
+
+            let indexer = Indexer::new(material);
+            if indexer.size != size {
+                Err(invalid(&format!(
+                    "{} has {} positions, expected {}",
+                    material, size, indexer.size
+                )))?;
+            }
+
+            let mut bytes = vec![0; size as usize * 2];
+            reader.read_exact(&mut bytes)?;
+            let values = bytes
+                .chunks_exact(2)
+                .map(|x| u16::from_le_bytes([x[0], x[1]]))
+                .collect();
+
+            tablebase.tables.insert(material, Table { indexer, values });
+        }
+
+        Ok(tablebase)
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let mut materials = self.tables.keys().collect::<Vec<_>>();
+        materials.sort();
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&(materials.len() as u32).to_le_bytes())?;
+
+        for material in materials {
+            let table = &self.tables[material];
+            writer.write_all(&[
+                material.octis[0],
+                material.octis[1],
+                material.arrows[0],
+                material.arrows[1],
+            ])?;
+            writer.write_all(&(table.values.len() as u64).to_le_bytes())?;
+            for value in &table.values {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    // Getters
+
+    pub fn materials(&self) -> Vec<Material> {
+        let mut materials = self.tables.keys().copied().collect::<Vec<_>>();
+        materials.sort();
+        materials
+    }
+
+    // the tables only hold positions of the full board
+    pub fn probe(&self, board: &Board) -> Option<TbValue> {
+        if board.bounds() != BoardBounds::default() {
+            return None;
+        }
+
+        let table = self.tables.get(&Material::of(board))?;
+        let index = table.indexer.index(board)?;
+        Some(decode(table.values[index as usize]))
+    }
+
+    // every move with the value it leaves the side to move, best first
+    pub fn score_moves(&self, board: &Board) -> Option<Vec<(OctiMove, TbValue)>> {
+        self.probe(board)?;
+
+        let mut scores = new_octi_move_iterator(board)
+            .filter_map(|octi_move| {
+                let mut next_board = *board;
+                next_board.make_move(&octi_move).unwrap();
+                let value = match self.child_value(&next_board)? {
+                    TbValue::Win(d) => TbValue::Loss(d + 1),
+                    TbValue::Loss(d) => TbValue::Win(d + 1),
+                    TbValue::Draw => TbValue::Draw,
+                };
+                Some((octi_move, value))
+            })
+            .collect::<Vec<_>>();
+
+        // faster wins and slower losses first
+        scores.sort_by_key(|(_, value)| {
+            Reverse(match value {
+                TbValue::Win(d) => (2, -(*d as i32)),
+                TbValue::Draw => (1, 0),
+                TbValue::Loss(d) => (0, *d as i32),
+            })
+        });
+        Some(scores)
+    }
+
+    // value of a position reached by a move, decided positions don't need a table
+    fn child_value(&self, board: &Board) -> Option<TbValue> {
+        match winner(board) {
+            Some(team) if team == board.turn() => Some(TbValue::Win(0)),
+            Some(_) => Some(TbValue::Loss(0)),
+            None => self.probe(board),
+        }
+    }
+
+    // retrograde analysis of a single material, every material it captures into is already solved
+    fn solve(&self, indexer: &Indexer, threads: usize) -> Vec<Entry> {
+        let size = indexer.size as usize;
+
+        // moves are generated once per position, decided positions and positions without moves
+        // start with their value
+        let positions = parallel_map(size, threads, |index| {
+            let board = indexer.board(index as u64);
+            match winner(&board) {
+                Some(team) if team == board.turn() => (encode(TbValue::Win(0)), vec![]),
+                Some(_) => (encode(TbValue::Loss(0)), vec![]),
+                None => {
+                    let children = self.children(indexer, &board);
+                    if children.is_empty() {
+                        (encode(TbValue::Loss(0)), children)
+                    } else {
+                        (DRAW, children)
+                    }
+                }
+            }
+        });
+
+        let mut values = positions.iter().map(|x| x.0).collect::<Vec<_>>();
+
+        // captures can lead to positions lost further away than anything in this material
+        let longest_capture = positions
+            .iter()
+            .flat_map(|x| x.1.iter())
+            .map(|x| match x {
+                Child::Known(entry) => match decode(*entry) {
+                    TbValue::Win(d) | TbValue::Loss(d) => d,
+                    TbValue::Draw => 0,
+                },
+                Child::Index(_) => 0,
+            })
+            .max()
+            .unwrap_or(0);
+
+        let mut ply = 1;
+        loop {
+            let updates = parallel_map(size, threads, |index| {
+                if values[index] != DRAW {
+                    return None;
+                }
+                resolve(&positions[index].1, &values, ply)
+            });
+
+            let mut changed = false;
+            for (index, update) in updates.into_iter().enumerate() {
+                if let Some(value) = update {
+                    values[index] = value;
+                    changed = true;
+                }
+            }
+
+            if !changed && ply > longest_capture + 1 {
+                break;
+            }
+            ply += 1;
+        }
+
+        values
+    }
+
+    fn children(&self, indexer: &Indexer, board: &Board) -> Vec<Child> {
+        new_octi_move_iterator(board)
+            .map(|octi_move| {
+                let mut next_board = *board;
+                next_board.make_move(&octi_move).unwrap();
+
+                match winner(&next_board) {
+                    Some(team) if team == next_board.turn() => {
+                        Child::Known(encode(TbValue::Win(0)))
+                    }
+                    Some(_) => Child::Known(encode(TbValue::Loss(0))),
+                    None if Material::of(&next_board) == indexer.material => {
+                        Child::Index(indexer.index(&next_board).unwrap() as u32)
+                    }
+                    None => Child::Known(encode(self.probe(&next_board).unwrap_or(TbValue::Draw))),
+                }
+            })
+            .collect()
+    }
+}
+
+// a position is won in ply plies if a move leads to a position lost in ply - 1 plies, and lost if
+// every move leads to a won position
+fn resolve(children: &[Child], values: &[Entry], ply: u16) -> Option<Entry> {
+    let mut shortest_loss = None;
+    let mut longest_win = Some(0);
+
+    for child in children {
+        let value = match child {
+            Child::Index(index) => decode(values[*index as usize]),
+            Child::Known(entry) => decode(*entry),
+        };
+
+        match value {
+            TbValue::Loss(d) => shortest_loss = Some(shortest_loss.map_or(d, |x: u16| x.min(d))),
+            TbValue::Win(d) => longest_win = longest_win.map(|x: u16| x.max(d)),
+            TbValue::Draw => longest_win = None,
+        }
+    }
+
+    match (shortest_loss, longest_win) {
+        (Some(d), _) if d < ply => Some(encode(TbValue::Win(d + 1))),
+        (None, Some(d)) if d < ply => Some(encode(TbValue::Loss(d + 1))),
+        _ => None,
+    }
+}
+
+impl Indexer {
+    fn new(material: Material) -> Indexer {
+        let arrow_sets = [0, 1].map(|i| {
+            let mut sets = Vec::new();
+            arrow_sets(
+                material.octis[i] as usize,
+                material.arrows[i] as u32,
+                0,
+                0,
+                &mut sets,
+            );
+            sets
+        });
+        let arrow_set_indices = [0, 1].map(|i| {
+            arrow_sets[i]
+                .iter()
+                .enumerate()
+                .map(|(index, set)| (*set, index as u64))
+                .collect::<HashMap<_, _>>()
+        });
+
+        let (red, green) = (material.octis[0] as u64, material.octis[1] as u64);
+        let size = binomial(SQUARES as u64, red)
+            * binomial(SQUARES as u64 - red, green)
+            * arrow_sets[0].len() as u64
+            * arrow_sets[1].len() as u64
+            * TEAMS as u64;
+
+        Indexer {
+            material,
+            arrow_sets,
+            arrow_set_indices,
+            size,
+        }
+    }
+
+    // assumes the board has the material of the indexer
+    fn index(&self, board: &Board) -> Option<u64> {
+        let mut squares = [[0u64; MAX_OCTIS]; TEAMS];
+        let mut arrow_sets = [0u64; TEAMS];
+        let mut counts = [0; TEAMS];
+
+        // octis are iterated in square order
+        for octi in board.octis() {
+            let team = team_index(octi.team());
+            let square = octi.pos().x() as u64 + octi.pos().y() as u64 * BOARD_WIDTH as u64;
+            squares[team][counts[team]] = square;
+            arrow_sets[team] |= (arrow_mask(octi) as u64) << (8 * counts[team]);
+            counts[team] += 1;
+        }
+
+        let (red, green) = (counts[0], counts[1]);
+        // green squares are ranked among the squares red doesn't occupy
+        let mut green_squares = squares[1];
+        for square in green_squares.iter_mut().take(green) {
+            *square -= squares[0][..red].iter().filter(|x| **x < *square).count() as u64;
+        }
+
+        let red_rank = rank(&squares[0][..red]);
+        let green_rank = rank(&green_squares[..green]);
+        let red_arrows = *self.arrow_set_indices[0].get(&arrow_sets[0])?;
+        let green_arrows = *self.arrow_set_indices[1].get(&arrow_sets[1])?;
+
+        let green_placements = binomial(SQUARES as u64 - red as u64, green as u64);
+        let mut index = red_rank * green_placements + green_rank;
+        index = index * self.arrow_sets[0].len() as u64 + red_arrows;
+        index = index * self.arrow_sets[1].len() as u64 + green_arrows;
+        index = index * TEAMS as u64 + team_index(board.turn()) as u64;
+        Some(index)
+    }
+
+    fn board(&self, index: u64) -> Board {
+        let (red, green) = (
+            self.material.octis[0] as usize,
+            self.material.octis[1] as usize,
+        );
+        let mut index = index;
+
+        let turn = if index % TEAMS as u64 == team_index(Team::Red) as u64 {
+            Team::Red
+        } else {
+            Team::Green
+        };
+        index /= TEAMS as u64;
+        let green_arrows = self.arrow_sets[1][(index % self.arrow_sets[1].len() as u64) as usize];
+        index /= self.arrow_sets[1].len() as u64;
+        let red_arrows = self.arrow_sets[0][(index % self.arrow_sets[0].len() as u64) as usize];
+        index /= self.arrow_sets[0].len() as u64;
+        let green_placements = binomial(SQUARES as u64 - red as u64, green as u64);
+        let red_squares = unrank(index / green_placements, red);
+        let green_squares = unrank(index % green_placements, green)
+            .into_iter()
+            .map(|x| {
+                // the x-th square red doesn't occupy
+                (0..SQUARES as u64)
+                    .filter(|square| !red_squares.contains(square))
+                    .nth(x as usize)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let mut octis = Vec::with_capacity(red + green);
+        let mut on_board = [0; TEAMS];
+        for (team, squares, arrow_set) in [
+            (Team::Red, &red_squares, red_arrows),
+            (Team::Green, &green_squares, green_arrows),
+        ] {
+            for (i, square) in squares.iter().enumerate() {
+                let mask = (arrow_set >> (8 * i)) as u8;
+                let arrs = [0, 1, 2, 3, 4, 5, 6, 7].map(|x| {
+                    if mask & (1 << x) != 0 {
+                        ArrowStatus::Active
+                    } else {
+                        ArrowStatus::Inactive
+                    }
+                });
+                on_board[team_index(team)] += mask.count_ones();
+
+                let pos = Position::new(
+                    (*square % BOARD_WIDTH as u64) as i32,
+                    (*square / BOARD_WIDTH as u64) as i32,
+                );
+                octis.push(Octi::new(octis.len() as u32, team, pos, arrs));
+            }
+        }
+
+        let arr_counts = [0, 1].map(|i| self.material.arrows[i] as u32 - on_board[i]);
+        Board::from_octis(turn, &octis, arr_counts)
+    }
+}
+
+// every combination of arrow masks for the remaining octis using at most arrows arrows
+fn arrow_sets(octis: usize, arrows: u32, set: u64, shift: usize, sets: &mut Vec<u64>) {
+    if octis == 0 {
+        sets.push(set);
+        return;
+    }
+
+    for mask in 0..=u8::MAX {
+        if mask.count_ones() <= arrows {
+            arrow_sets(
+                octis - 1,
+                arrows - mask.count_ones(),
+                set | (mask as u64) << shift,
+                shift + 8,
+                sets,
+            );
+        }
+    }
+}
+
+fn arrow_mask(octi: &Octi) -> u8 {
+    octi.arr_iter()
+        .filter(|(_, x)| **x == ArrowStatus::Active)
+        .fold(0, |acc, (i, _)| acc | 1 << i)
+}
+
+fn binomial(n: u64, k: u64) -> u64 {
+    if k > n {
+        return 0;
+    }
+    (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1))
+}
+
+// combinatorial number system, squares are sorted ascending
+fn rank(squares: &[u64]) -> u64 {
+    squares
+        .iter()
+        .enumerate()
+        .map(|(i, x)| binomial(*x, i as u64 + 1))
+        .sum()
+}
+
+fn unrank(rank: u64, k: usize) -> Vec<u64> {
+    let mut rank = rank;
+    let mut squares = vec![0; k];
+    for i in (1..=k).rev() {
+        let mut square = i as u64 - 1;
+        while binomial(square + 1, i as u64) <= rank {
+            square += 1;
+        }
+        rank -= binomial(square, i as u64);
+        squares[i - 1] = square;
+    }
+    squares
+}
+
+pub fn encode(value: TbValue) -> Entry {
+    match value {
+        TbValue::Win(d) => 1 + 2 * d,
+        TbValue::Loss(d) => 2 + 2 * d,
+        TbValue::Draw => DRAW,
+    }
+}
+
+pub fn decode(entry: Entry) -> TbValue {
+    if entry == DRAW {
+        TbValue::Draw
+    } else if entry % 2 == 1 {
+        TbValue::Win((entry - 1) / 2)
+    } else {
+        TbValue::Loss((entry - 2) / 2)
+    }
+}
+
+// f over 0..size split between threads, in order
+fn parallel_map<T: Send, F: Fn(usize) -> T + Sync>(size: usize, threads: usize, f: F) -> Vec<T> {
+    let chunk = (size + threads - 1) / threads.max(1);
+    let f = &f;
+
+    thread::scope(|scope| {
+        let handles = (0..threads)
+            .map(|i| {
+                let (start, end) = ((i * chunk).min(size), ((i + 1) * chunk).min(size));
+                scope.spawn(move || (start..end).map(f).collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|x| x.join().unwrap())
+            .collect()
+    })
+}
+
+impl Display for Material {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}v{} ({}+{} arrows)",
+            self.octis[0], self.octis[1], self.arrows[0], self.arrows[1]
+        )
+    }
+}
+
+impl Display for TbValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TbValue::Win(d) => write!(f, "win in {} plies", d),
+            TbValue::Loss(d) => write!(f, "loss in {} plies", d),
+            TbValue::Draw => write!(f, "draw"),
+        }
+    }
+}
+
+impl Default for Tablebase {
+    fn default() -> Self {
+        Tablebase::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::board;
+    use super::*;
+
+    // red walks its octi up to green's home in two moves, green's can only walk away
+    const RED_TO_MOVE: &str = "6/6/6/2R[2]3/G[2]5/6/6 r 0 0";
+    const GREEN_TO_MOVE: &str = "6/6/6/2R[2]3/G[2]5/6/6 g 0 0";
+
+    fn probe(tablebase: &Tablebase, position: &str) -> Option<TbValue> {
+        let position: board::Board = position.parse().unwrap();
+        tablebase.probe(&Board::new(&position))
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        for d in 0..1000 {
+            for value in [TbValue::Win(d), TbValue::Loss(d), TbValue::Draw] {
+                assert_eq!(decode(encode(value)), value);
+            }
+        }
+    }
+
+    #[test]
+    fn known_values() {
+        let tablebase = Tablebase::generate(Material::new(1, 1, 1, 1), 2, |_, _| {}).unwrap();
+        assert_eq!(probe(&tablebase, RED_TO_MOVE), Some(TbValue::Win(3)));
+        assert_eq!(probe(&tablebase, GREEN_TO_MOVE), Some(TbValue::Loss(4)));
+        // other materials aren't in the tables
+        assert_eq!(probe(&tablebase, "6/1GGGG1/6/6/6/1RRRR1/6 r 12 12"), None);
+    }
+
+    #[test]
+    fn write_then_read() {
+        let tablebase = Tablebase::generate(Material::new(1, 1, 1, 1), 2, |_, _| {}).unwrap();
+        let path = std::env::temp_dir().join(format!("oxidized_octi_{}.octb", std::process::id()));
+        tablebase.write(&path).unwrap();
+        let read = Tablebase::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        let read = read.unwrap();
+
+        assert_eq!(read.materials(), tablebase.materials());
+        for material in tablebase.materials() {
+            assert!(read.tables[&material].values == tablebase.tables[&material].values);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use super::super::board::Team;
+
+use super::board::Board;
+use super::eval::{eval_terms, EvalData};
+
+// Texel style tuning: the evaluation is squashed into an expected result with
+// 1 / (1 + e^(-eval / scale)) and the mean squared error against the real game results is
+// minimized over the parameters of the eval data
+
+pub struct TuneConfig {
+    pub epochs: u32,
+    // adam step size, in eval points
+    pub learning_rate: f64,
+}
+
+pub struct TuneResult {
+    pub eval_data: EvalData,
+    pub scale: f64,
+    pub error_before: f64,
+    pub error_after: f64,
+}
+
+// board_eval is linear in its parameters, so every position is reduced once to the coefficients
+// of the parameters it uses
+struct Sample {
+    features: Vec<(usize, f64)>,
+    // expected result from red's perspective, 1 is a red win and 0 a green win
+    result: f64,
+}
+
+// positions are paired with the result of the game they were taken from, from red's perspective
+pub fn tune<F: FnMut(u32, f64)>(
+    positions: &[(Board, f64)],
+    base: &EvalData,
+    config: &TuneConfig,
+    mut progress: F,
+) -> Result<TuneResult, String> {
+    let samples = positions
+        .iter()
+        .filter_map(|(board, result)| sample(board, *result, base))
+        .collect::<Vec<_>>();
+
+    if samples.is_empty() {
+        Err("No undecided positions to tune on")?;
+    }
+
+    let mut weights = base
+        .parameters()
+        .into_iter()
+        .map(|x| x as f64)
+        .collect::<Vec<_>>();
+
+    let scale = fit_scale(&samples, &weights);
+    let error_before = error(&samples, &weights, scale);
+
+    // adam
+    let (beta1, beta2, epsilon) = (0.9, 0.999, 1e-8);
+    let mut m = vec![0.0; weights.len()];
+    let mut v = vec![0.0; weights.len()];
+
+    for epoch in 1..=config.epochs {
+        let gradient = gradient(&samples, &weights, scale);
+
+        for i in 0..weights.len() {
+            m[i] = beta1 * m[i] + (1.0 - beta1) * gradient[i];
+            v[i] = beta2 * v[i] + (1.0 - beta2) * gradient[i] * gradient[i];
+            let m_hat = m[i] / (1.0 - beta1.powi(epoch as i32));
+            let v_hat = v[i] / (1.0 - beta2.powi(epoch as i32));
+            weights[i] -= config.learning_rate * m_hat / (v_hat.sqrt() + epsilon);
+        }
+
+        progress(epoch, error(&samples, &weights, scale));
+    }
+
+    let parameters = weights.iter().map(|x| x.round() as i32).collect::<Vec<_>>();
+    let rounded = parameters.iter().map(|x| *x as f64).collect::<Vec<_>>();
+
+    Ok(TuneResult {
+        eval_data: base.with_parameters(&parameters),
+        scale,
+        error_before,
+        error_after: error(&samples, &rounded, scale),
+    })
+}
+
+// decided positions are evaluated as wins regardless of the parameters, so they are skipped
+fn sample(board: &Board, result: f64, eval_data: &EvalData) -> Option<Sample> {
+    let mut features = Vec::new();
+    let game_winner = eval_terms(board, |team, term| {
+        let coefficient = match team {
+            Team::Red => 1.0,
+            Team::Green => -1.0,
+        };
+        features.push((eval_data.term_index(team, term), coefficient));
+    });
+
+    if game_winner.is_some() {
+        return None;
+    }
+
+    features.sort_by_key(|x| x.0);
+    let mut merged: Vec<(usize, f64)> = Vec::with_capacity(features.len());
+    for (index, coefficient) in features {
+        match merged.last_mut() {
+            Some(last) if last.0 == index => last.1 += coefficient,
+            _ => merged.push((index, coefficient)),
+        }
+    }
+    merged.retain(|x| x.1 != 0.0);
+
+    Some(Sample {
+        features: merged,
+        result,
+    })
+}
+
+fn evaluate(sample: &Sample, weights: &[f64]) -> f64 {
+    sample.features.iter().map(|(i, c)| weights[*i] * c).sum()
+}
+
+fn sigmoid(eval: f64, scale: f64) -> f64 {
+    1.0 / (1.0 + (-eval / scale).exp())
+}
+
+fn error(samples: &[Sample], weights: &[f64], scale: f64) -> f64 {
+    samples
+        .iter()
+        .map(|x| (x.result - sigmoid(evaluate(x, weights), scale)).powi(2))
+        .sum::<f64>()
+        / samples.len() as f64
+}
+
+fn gradient(samples: &[Sample], weights: &[f64], scale: f64) -> Vec<f64> {
+    let mut gradient = vec![0.0; weights.len()];
+
+    for sample in samples {
+        let p = sigmoid(evaluate(sample, weights), scale);
+        let d = -2.0 * (sample.result - p) * p * (1.0 - p) / scale;
+        for (i, c) in &sample.features {
+            gradient[*i] += d * c;
+        }
+    }
+
+    let n = samples.len() as f64;
+    gradient.iter_mut().for_each(|x| *x /= n);
+    gradient
+}
+
+// golden section search over log(scale), the error is unimodal in it for practical data
+fn fit_scale(samples: &[Sample], weights: &[f64]) -> f64 {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut lo, mut hi) = (0f64, 1e6f64.ln());
+    let f = |x: f64| error(samples, weights, x.exp());
+
+    for _ in 0..100 {
+        let a = hi - ratio * (hi - lo);
+        let b = lo + ratio * (hi - lo);
+        if f(a) < f(b) {
+            hi = b;
+        } else {
+            lo = a;
+        }
+    }
+
+    ((lo + hi) / 2.0).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::board;
+    use super::*;
+
+    // positions where a team is octis ahead, with that team winning, and two even ones
+    const POSITIONS: [(&str, f64); 8] = [
+        ("6/1GGGG1/6/6/6/1RRRR1/6 r 12 12", 0.5),
+        ("6/1GGGG1/6/6/6/1RRRR1/6 g 11 12", 0.5),
+        ("6/1G4/6/6/6/1RRRR1/6 r 12 12", 1.0),
+        ("6/1G2G1/6/6/6/1RRRR1/6 g 12 12", 1.0),
+        ("6/1GG[0]GG1/6/6/6/1RRR2/6 g 12 11", 1.0),
+        ("6/1GGGG1/6/6/6/1R4/6 g 12 12", 0.0),
+        ("6/1GGGG1/6/6/6/2R1R1/6 r 12 12", 0.0),
+        ("6/1GGGG1/6/6/6/2RR[0]R1/6 r 11 12", 0.0),
+    ];
+
+    #[test]
+    fn tune_lowers_the_error() {
+        let positions = POSITIONS
+            .iter()
+            .map(|(position, result)| {
+                let position: board::Board = position.parse().unwrap();
+                (Board::new(&position), *result)
+            })
+            .collect::<Vec<_>>();
+        let config = TuneConfig {
+            epochs: 100,
+            learning_rate: 1.0,
+        };
+
+        let mut errors = Vec::new();
+        let result = tune(&positions, &EvalData::default(), &config, |_, error| {
+            errors.push(error)
+        })
+        .unwrap();
+
+        assert!(result.scale > 0.0);
+        assert_eq!(errors.len(), 100);
+        assert!(result.error_after < result.error_before / 2.0);
+        assert!(errors[99] < errors[0]);
+    }
+}
@@ -0,0 +1,391 @@
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::super::board::{BoardBounds, Boardable, OctiMove, Position};
+
+use super::board::{Board, BoardKey, Symmetry, UndoStack};
+use super::moveiter::new_octi_move_iterator;
+use super::tablebase::{decode, encode, TbValue};
+use super::{winner, BOARD_HEIGHT, BOARD_WIDTH};
+
+// Solving reduced boards
+//
+// a variant is a starting position on a board smaller than the full one, with fewer octis and
+// arrows, the home squares follow the board size. Every position reachable from the start is
+// enumerated once per symmetry class (values are from the perspective of the side to move, so
+// every symmetry keeps them), then solved by retrograde analysis over the move graph: a position
+// is won in n plies if a move leads to a position lost in n - 1 plies, and lost in n plies if
+// every move leads to a won position, the longest of them being won in n - 1 plies. Positions
+// never resolved are draws.
+//
+// file format (little endian): "OCVS", version (u8), width and height (u8), the start key, the
+// position count (u64), then for every position its canonical key and its value (u16, encoded
+// like the tablebases), keys are stored as BoardKey::to_bytes makes them
+
+const MAGIC: &[u8; 4] = b"OCVS";
+const VERSION: u8 = 1;
+// positions enumerated between calls of progress
+const PROGRESS_POSITIONS: usize = 1_000_000;
+
+pub struct VariantConfig {
+    // the enumeration gives up past this many positions, each takes a few hundred bytes while
+    // solving
+    pub max_positions: usize,
+}
+
+pub struct Variant {
+    bounds: BoardBounds,
+    start: BoardKey,
+    // indexed by canonical key
+    values: HashMap<BoardKey, TbValue>,
+}
+
+impl Variant {
+    // progress gets the number of positions enumerated so far
+    pub fn solve<F: FnMut(usize)>(
+        start: &Board,
+        config: &VariantConfig,
+        mut progress: F,
+    ) -> Result<Variant, String> {
+        let bounds = start.bounds();
+        if bounds.width() > BOARD_WIDTH as i32 || bounds.height() > BOARD_HEIGHT as i32 {
+            Err(format!(
+                "Boards larger than {}x{} are not supported",
+                BOARD_WIDTH, BOARD_HEIGHT
+            ))?;
+        }
+
+        // breadth first from the start, positions are expanded in the order they are found, so
+        // the keys double as the queue, the children of a position are stored as indices
+        let mut indices = HashMap::new();
+        let mut keys = Vec::new();
+        let mut first_child = vec![0];
+        let mut children: Vec<u32> = Vec::new();
+
+        let key = start.canonical_key(&Symmetry::ALL).0;
+        indices.insert(key, 0u32);
+        keys.push(key);
+
+        let mut undo = UndoStack::new();
+        while first_child.len() <= keys.len() {
+            let mut board = Board::from_key(&keys[first_child.len() - 1], bounds);
+            if winner(&board).is_none() {
+                for octi_move in new_octi_move_iterator(&board).collect::<Vec<_>>() {
+                    board.do_move(&octi_move, &mut undo);
+                    let key = board.canonical_key(&Symmetry::ALL).0;
+                    let index = *indices.entry(key).or_insert_with(|| {
+                        keys.push(key);
+                        keys.len() as u32 - 1
+                    });
+                    children.push(index);
+                    board.undo_move(&mut undo);
+                }
+            }
+            first_child.push(children.len());
+
+            if keys.len() > config.max_positions {
+                Err(format!(
+                    "More than {} positions are reachable",
+                    config.max_positions
+                ))?;
+            }
+            let expanded = first_child.len() - 1;
+            if expanded % PROGRESS_POSITIONS == 0 {
+                progress(expanded);
+            }
+        }
+        drop(indices);
+
+        let values = retrograde(&keys, &first_child, &children, bounds);
+        Ok(Variant {
+            bounds,
+            start: key,
+            values: keys.into_iter().zip(values).collect(),
+        })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Variant, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(
+            File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?,
+        );
+        let invalid = |e: &str| format!("Invalid variant {}: {}", path.display(), e);
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            Err(invalid("not a variant file"))?;
+        }
+        let mut header = [0; 3];
+        reader.read_exact(&mut header)?;
+        if header[0] != VERSION {
+            Err(invalid(&format!("unsupported version {}", header[0])))?;
+        }
+        let (width, height) = (header[1] as i32, header[2] as i32);
+        if width == 0 || width > BOARD_WIDTH as i32 || height == 0 || height > BOARD_HEIGHT as i32 {
+            Err(invalid(&format!("unsupported size {}x{}", width, height)))?;
+        }
+        let bounds = BoardBounds::new(Position::new(0, 0), Position::new(width - 1, height - 1));
+
+        let mut key = vec![0; BoardKey::byte_len(bounds)];
+        reader.read_exact(&mut key)?;
+        let start = BoardKey::from_bytes(&key, bounds).map_err(|e| invalid(&e))?;
+
+        let mut count = [0; 8];
+        reader.read_exact(&mut count)?;
+        let mut values = HashMap::new();
+        for _ in 0..u64::from_le_bytes(count) {
+            reader.read_exact(&mut key)?;
+            let mut value = [0; 2];
+            reader.read_exact(&mut value)?;
+            values.insert(
+                BoardKey::from_bytes(&key, bounds).map_err(|e| invalid(&e))?,
+                decode(u16::from_le_bytes(value)),
+            );
+        }
+
+        Ok(Variant {
+            bounds,
+            start,
+            values,
+        })
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        // sorted, so the same variant always makes the same file
+        let mut keys = self.values.keys().collect::<Vec<_>>();
+        keys.sort();
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[
+            VERSION,
+            self.bounds.width() as u8,
+            self.bounds.height() as u8,
+        ])?;
+        writer.write_all(&self.start.to_bytes(self.bounds))?;
+        writer.write_all(&(keys.len() as u64).to_le_bytes())?;
+        for key in keys {
+            writer.write_all(&key.to_bytes(self.bounds))?;
+            writer.write_all(&encode(self.values[key]).to_le_bytes())?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    // Getters
+
+    pub fn start(&self) -> Board {
+        Board::from_key(&self.start, self.bounds)
+    }
+
+    // the number of positions, one per symmetry class
+    pub fn size(&self) -> usize {
+        self.values.len()
+    }
+
+    // None for positions of other bounds or not reachable from the start
+    pub fn probe(&self, board: &Board) -> Option<TbValue> {
+        if board.bounds() != self.bounds {
+            return None;
+        }
+
+        self.values
+            .get(&board.canonical_key(&Symmetry::ALL).0)
+            .copied()
+    }
+
+    // the move keeping the best value for the side to move: the fastest win, a draw or the
+    // slowest loss, None if the position isn't solved or has no moves
+    pub fn best_move(&self, board: &Board) -> Option<(OctiMove, TbValue)> {
+        self.probe(board)?;
+
+        let mut board = *board;
+        let mut undo = UndoStack::new();
+        let mut best: Option<(OctiMove, TbValue)> = None;
+        for octi_move in new_octi_move_iterator(&board).collect::<Vec<_>>() {
+            board.do_move(&octi_move, &mut undo);
+            let value = match self.probe(&board)? {
+                TbValue::Win(d) => TbValue::Loss(d + 1),
+                TbValue::Loss(d) => TbValue::Win(d + 1),
+                TbValue::Draw => TbValue::Draw,
+            };
+            board.undo_move(&mut undo);
+
+            if best.as_ref().is_none_or(|x| rank(value) > rank(x.1)) {
+                best = Some((octi_move, value));
+            }
+        }
+        best
+    }
+
+    // every solved position in key order, with its value
+    pub fn positions(&self) -> Vec<(Board, TbValue)> {
+        let mut keys = self.values.keys().collect::<Vec<_>>();
+        keys.sort();
+        keys.into_iter()
+            .map(|x| (Board::from_key(x, self.bounds), self.values[x]))
+            .collect()
+    }
+}
+
+// values of the positions, children of position i are children[first_child[i]..first_child[i + 1]]
+//
+// positions are resolved in order of their distance, so the first lost child a position gets is
+// the closest and the last won child the furthest
+fn retrograde(
+    keys: &[BoardKey],
+    first_child: &[usize],
+    children: &[u32],
+    bounds: BoardBounds,
+) -> Vec<TbValue> {
+    let positions = keys.len();
+
+    let mut first_parent = vec![0; positions + 1];
+    for child in children {
+        first_parent[*child as usize + 1] += 1;
+    }
+    for i in 0..positions {
+        first_parent[i + 1] += first_parent[i];
+    }
+    let mut parents = vec![0u32; children.len()];
+    let mut next = first_parent.clone();
+    for parent in 0..positions {
+        for child in &children[first_child[parent]..first_child[parent + 1]] {
+            parents[next[*child as usize]] = parent as u32;
+            next[*child as usize] += 1;
+        }
+    }
+
+    // decided positions and positions without moves start resolved
+    let mut values = vec![None; positions];
+    let mut unresolved = (0..positions)
+        .map(|x| first_child[x + 1] - first_child[x])
+        .collect::<Vec<_>>();
+    let mut queue = VecDeque::new();
+    for (i, key) in keys.iter().enumerate() {
+        let board = Board::from_key(key, bounds);
+        let value = match winner(&board) {
+            Some(team) if team == board.turn() => TbValue::Win(0),
+            Some(_) => TbValue::Loss(0),
+            None if unresolved[i] == 0 => TbValue::Loss(0),
+            None => continue,
+        };
+        values[i] = Some(value);
+        queue.push_back(i);
+    }
+
+    while let Some(child) = queue.pop_front() {
+        for parent in &parents[first_parent[child]..first_parent[child + 1]] {
+            let parent = *parent as usize;
+            if values[parent].is_some() {
+                continue;
+            }
+
+            match values[child].unwrap() {
+                TbValue::Loss(d) => {
+                    values[parent] = Some(TbValue::Win(d + 1));
+                    queue.push_back(parent);
+                }
+                TbValue::Win(d) => {
+                    unresolved[parent] -= 1;
+                    if unresolved[parent] == 0 {
+                        values[parent] = Some(TbValue::Loss(d + 1));
+                        queue.push_back(parent);
+                    }
+                }
+                TbValue::Draw => unreachable!(),
+            }
+        }
+    }
+
+    values
+        .into_iter()
+        .map(|x| x.unwrap_or(TbValue::Draw))
+        .collect()
+}
+
+// orders values for the side to move, faster wins and slower losses are better
+fn rank(value: TbValue) -> (u8, i32) {
+    match value {
+        TbValue::Win(d) => (2, -(d as i32)),
+        TbValue::Draw => (1, 0),
+        TbValue::Loss(d) => (0, d as i32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::board;
+    use super::super::opponent;
+    use super::super::solve::{solve, Outcome, SolveConfig};
+    use super::*;
+
+    fn variant(position: &str) -> Variant {
+        let position: board::Board = position.parse().unwrap();
+        let config = VariantConfig {
+            max_positions: 100_000,
+        };
+        Variant::solve(&Board::new(&position), &config, |_| {}).unwrap()
+    }
+
+    #[test]
+    fn known_values() {
+        for (position, size, value) in [
+            ("3/1G1/3/1R1/3 r 2 2", 5367, TbValue::Loss(8)),
+            ("4/1G2/4/2R1/4 r 2 2", 12813, TbValue::Win(7)),
+        ] {
+            let variant = variant(position);
+            assert_eq!(variant.size(), size);
+            let start = variant.start();
+            assert_eq!(variant.probe(&start), Some(value));
+
+            // the best moves of both sides end the game right on time
+            let (mut board, mut undo) = (start, UndoStack::new());
+            let plies = match value {
+                TbValue::Win(d) | TbValue::Loss(d) => d,
+                TbValue::Draw => unreachable!(),
+            };
+            for _ in 0..plies {
+                assert!(winner(&board).is_none());
+                let (octi_move, _) = variant.best_move(&board).unwrap();
+                board.do_move(&octi_move, &mut undo);
+            }
+            let loser = match value {
+                TbValue::Win(_) => opponent(start.turn()),
+                _ => start.turn(),
+            };
+            assert_eq!(winner(&board), Some(opponent(loser)));
+        }
+    }
+
+    #[test]
+    fn agrees_with_proof_number_search() {
+        let variant = variant("3/1G1/3/1R1/3 r 2 2");
+        for (board, value) in variant.positions().into_iter().step_by(20) {
+            let outcome = solve(&board, &SolveConfig { max_nodes: 5_000 }).outcome();
+            match value {
+                TbValue::Win(_) => assert_eq!(outcome, Outcome::Won),
+                TbValue::Loss(_) => assert_eq!(outcome, Outcome::Lost),
+                TbValue::Draw => assert_eq!(outcome, Outcome::Unknown),
+            }
+        }
+    }
+
+    #[test]
+    fn write_then_read() {
+        let variant = variant("3/1G1/3/1R1/3 r 2 2");
+        let path = std::env::temp_dir().join(format!("oxidized_octi_{}.ocvs", std::process::id()));
+        variant.write(&path).unwrap();
+        let read = Variant::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        let read = read.unwrap();
+
+        assert!(read.bounds == variant.bounds && read.start == variant.start);
+        assert!(read.values == variant.values);
+    }
+}
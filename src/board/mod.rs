@@ -12,11 +12,19 @@ use std::ops;
 pub type Direction = Position;
 pub type OctiID = u32;
 
+//
+// Constants
+//
+
+// the arrow pointing the other way horizontally or vertically, indexed by arrow value
+const HORIZONTAL_FLIP: [usize; ARROWS_PER_OCTI] = [4, 3, 2, 1, 0, 7, 6, 5];
+const VERTICAL_FLIP: [usize; ARROWS_PER_OCTI] = [0, 7, 6, 5, 4, 3, 2, 1];
+
 //
 // Enums
 //
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub enum OctiMove {
     Arrow(Position, Arrow),
     Move(Position, Vec<(Arrow, bool)>),
@@ -64,13 +72,13 @@ pub struct Octi {
     arrs: [ArrowStatus; ARROWS_PER_OCTI],
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub struct Arrow(usize);
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, PartialOrd, Ord, Hash)]
 pub struct Position(i32, i32);
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct BoardBounds(Position, Position);
 
 impl Board {
@@ -247,9 +255,13 @@ impl Octi {
 
     // modification
 
+    // only the arrows are flipped, the position depends on the board
     pub fn horizontal_flip(&mut self) {
-        let flip_mapping = [4, 3, 2, 1, 0, 7, 6, 5];
-        self.arrs = flip_mapping.map(|i| self.arrs[i]);
+        self.arrs = HORIZONTAL_FLIP.map(|i| self.arrs[i]);
+    }
+
+    pub fn vertical_flip(&mut self) {
+        self.arrs = VERTICAL_FLIP.map(|i| self.arrs[i]);
     }
 }
 
@@ -279,6 +291,14 @@ impl Arrow {
             _ => panic!("Something went terribly wrong, arrow value exceeds max"),
         }
     }
+
+    pub fn horizontal_flip(&self) -> Arrow {
+        Arrow(HORIZONTAL_FLIP[self.0])
+    }
+
+    pub fn vertical_flip(&self) -> Arrow {
+        Arrow(VERTICAL_FLIP[self.0])
+    }
 }
 
 impl Position {
@@ -1,3 +1,4 @@
+use super::super::global::{BOARD_HEIGHT, BOARD_WIDTH};
 use super::*;
 use std::collections::VecDeque;
 use std::fmt::Display;
@@ -111,6 +112,9 @@ impl FromStr for Position {
 
         let s: String = chars.into_iter().collect();
         let coords: Vec<_> = s.split(',').collect();
+        if coords.len() != 2 {
+            Err(format!("Position does not have two coordinates: {}", s))?;
+        }
 
         let x = coords[0]
             .parse::<i32>()
@@ -122,3 +126,172 @@ impl FromStr for Position {
         Ok(Position::new(x, y))
     }
 }
+
+// position notation, rows from y = 0 down separated by '/', runs of empty cells as numbers and
+// octis as R or G followed by their arrows in brackets, then the turn and the arrow counts of red
+// and green, e.g. the default board:
+//
+// 6/1GGGG1/6/6/6/1RRRR1/6 r 12 12
+impl Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (lu, rd) = (self.bounds.lu(), self.bounds.rd());
+        let mut rows = Vec::with_capacity(self.bounds.height() as usize);
+
+        for y in lu.y()..=rd.y() {
+            let mut row = String::new();
+            let mut empty = 0;
+
+            for x in lu.x()..=rd.x() {
+                match self.get_octi_by_pos(&Position::new(x, y)) {
+                    Some(octi) => {
+                        if empty > 0 {
+                            row.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        row.push(match octi.team() {
+                            Team::Red => 'R',
+                            Team::Green => 'G',
+                        });
+                        if octi.arr_count() > 0 {
+                            row.push('[');
+                            for (i, arr) in octi.arr_iter() {
+                                if *arr == ArrowStatus::Active {
+                                    row.push_str(&i.to_string());
+                                }
+                            }
+                            row.push(']');
+                        }
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                row.push_str(&empty.to_string());
+            }
+            rows.push(row);
+        }
+
+        write!(
+            f,
+            "{} {} {} {}",
+            rows.join("/"),
+            match self.turn {
+                Team::Red => 'r',
+                Team::Green => 'g',
+            },
+            self.get_arr_count(&Team::Red).unwrap_or(0),
+            self.get_arr_count(&Team::Green).unwrap_or(0)
+        )
+    }
+}
+
+impl FromStr for Board {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let args: Vec<&str> = s.split_whitespace().collect();
+
+        if args.len() != 4 {
+            Err(format!("Invalid number of position fields: {}", args.len()))?;
+        }
+
+        let turn = match args[1] {
+            "r" => Team::Red,
+            "g" => Team::Green,
+            _ => Err(format!("Invalid turn: {}", args[1]))?,
+        };
+
+        let mut arr_counts = BTreeMap::new();
+        arr_counts.insert(
+            Team::Red,
+            args[2]
+                .parse()
+                .map_err(|_| format!("Invalid red arrow count: {}", args[2]))?,
+        );
+        arr_counts.insert(
+            Team::Green,
+            args[3]
+                .parse()
+                .map_err(|_| format!("Invalid green arrow count: {}", args[3]))?,
+        );
+
+        let mut octis = BTreeMap::new();
+        let mut pos_indexer = BTreeMap::new();
+        let mut width = None;
+        let rows: Vec<&str> = args[0].split('/').collect();
+
+        for (y, row) in rows.iter().enumerate() {
+            let mut chars = row.chars().peekable();
+            let mut x = 0;
+
+            while let Some(c) = chars.next() {
+                let team = match c {
+                    'R' => Team::Red,
+                    'G' => Team::Green,
+                    '0'..='9' => {
+                        let mut empty = c.to_digit(10).unwrap();
+                        while let Some(d) = chars.peek().and_then(|x| x.to_digit(10)) {
+                            empty = empty * 10 + d;
+                            chars.next();
+                        }
+                        x += empty as i32;
+                        continue;
+                    }
+                    _ => Err(format!("Invalid character in row {}: {}", y, c))?,
+                };
+
+                let mut arrs = [ArrowStatus::Inactive; ARROWS_PER_OCTI];
+                if chars.peek() == Some(&'[') {
+                    chars.next();
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(d) => {
+                                let arr: Arrow = d.to_string().parse()?;
+                                arrs[arr.value()] = ArrowStatus::Active;
+                            }
+                            None => Err(format!("Unclosed arrows in row {}", y))?,
+                        }
+                    }
+                }
+
+                let pos = Position::new(x, y as i32);
+                let id = octis.len() as OctiID;
+                octis.insert(id, Octi::new(id, team, pos, arrs));
+                pos_indexer.insert(pos, id);
+                x += 1;
+            }
+
+            match width {
+                None => width = Some(x),
+                Some(width) if width != x => {
+                    Err(format!("Row {} has width {}, expected {}", y, x, width))?
+                }
+                _ => {}
+            }
+        }
+
+        let width = width.unwrap_or(0);
+        if width == 0 {
+            Err("Empty board")?;
+        }
+        if width > BOARD_WIDTH as i32 || rows.len() > BOARD_HEIGHT {
+            Err(format!(
+                "Boards larger than {}x{} are not supported",
+                BOARD_WIDTH, BOARD_HEIGHT
+            ))?;
+        }
+
+        Ok(Board {
+            turn,
+            bounds: BoardBounds::new(
+                Position::new(0, 0),
+                Position::new(width - 1, rows.len() as i32 - 1),
+            ),
+            next_id: octis.len() as OctiID,
+            octis,
+            pos_indexer,
+            arr_counts,
+        })
+    }
+}
@@ -0,0 +1,67 @@
+use std::error::Error;
+
+use super::super::ai::eval::EvalData;
+use super::super::ai::priority::PriorityEvalData;
+use super::super::ai::tablebase::Tablebase;
+use super::super::board::Board;
+use super::super::game::analysis::{analyze, Limit};
+use super::super::game::record::{read_records, write_records, GameRecord};
+use super::Flags;
+
+// oxidized_octi analyze --games <path> --out <path> [--limit <depth|time>] [--eval <path>]
+//                       [--priority <path>] [--tablebase <path>]
+//
+// annotates every move of the game records, the limit is a depth or a time per position like 2s
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = Flags::parse(
+        args,
+        &["games", "out", "limit", "eval", "priority", "tablebase"],
+    )?;
+
+    let games = flags.value("games").ok_or("Missing --games")?;
+    let out = flags.value("out").ok_or("Missing --out")?;
+    let limit = match flags.value("limit") {
+        Some(limit) => limit.parse()?,
+        None => Limit::Depth(4),
+    };
+
+    let eval_data = match flags.value("eval") {
+        Some(path) => EvalData::from_file(path)?,
+        None => EvalData::default(),
+    };
+    let priority_eval_data = match flags.value("priority") {
+        Some(path) => PriorityEvalData::from_file(path)?,
+        None => PriorityEvalData::default(),
+    };
+    let tablebase = match flags.value("tablebase") {
+        Some(path) => Some(Tablebase::from_file(path)?),
+        None => None,
+    };
+
+    let records = read_records(games)?;
+    let mut analyzed = Vec::with_capacity(records.len());
+    for (i, record) in records.iter().enumerate() {
+        let annotations = analyze(
+            &Board::default(),
+            record.moves(),
+            limit,
+            &eval_data,
+            &priority_eval_data,
+            tablebase.as_ref(),
+        )
+        .map_err(|e| format!("Invalid game {}: {}", i, e))?;
+
+        let flagged = annotations
+            .iter()
+            .filter(|x| x.judgement().is_some())
+            .count();
+        println!("{}/{} games, {} moves flagged", i + 1, records.len(), flagged);
+
+        let mut record = GameRecord::new(record.result(), record.moves().to_vec());
+        record.set_annotations(annotations.into_iter().map(Some).collect());
+        analyzed.push(record);
+    }
+
+    write_records(out, &analyzed)?;
+    Ok(())
+}
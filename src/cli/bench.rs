@@ -0,0 +1,150 @@
+use std::error::Error;
+use std::sync::atomic::AtomicBool;
+use std::time::Instant;
+
+use super::super::ai::board;
+use super::super::ai::eval::EvalData;
+use super::super::ai::priority::PriorityEvalData;
+use super::super::ai::{iterative_deepening, MinimaxConfig, SearchControl};
+use super::super::board::Board;
+use super::Flags;
+
+// oxidized_octi bench [--depth <n>] [--eval <path>] [--priority <path>]
+//
+// searches a fixed set of positions with the default search, each to its own depth or to the
+// given one, and prints the nodes of each and the total with the time it took. The searches don't
+// depend on the time, so the node total only changes with the search, the evaluation or the move
+// generation
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = Flags::parse(args, &["depth", "eval", "priority"])?;
+
+    let depth: Option<u32> = flags.get("depth")?;
+    if depth == Some(0) {
+        Err("--depth cannot be 0")?;
+    }
+    let eval_data = match flags.value("eval") {
+        Some(path) => EvalData::from_file(path)?,
+        None => EvalData::default(),
+    };
+    let priority_eval_data = match flags.value("priority") {
+        Some(path) => PriorityEvalData::from_file(path)?,
+        None => PriorityEvalData::default(),
+    };
+
+    let start = Instant::now();
+    let total_nodes = bench(
+        depth,
+        &eval_data,
+        &priority_eval_data,
+        |i, depth, nodes, octi_move| {
+            println!(
+                "{}/{}: depth {}, {} nodes, {}",
+                i + 1,
+                POSITIONS.len(),
+                depth,
+                nodes,
+                octi_move
+            )
+        },
+    )?;
+
+    let elapsed = start.elapsed();
+    println!(
+        "{} nodes in {:.3}s ({:.0} nodes/s)",
+        total_nodes,
+        elapsed.as_secs_f64(),
+        total_nodes as f64 / elapsed.as_secs_f64()
+    );
+    Ok(())
+}
+
+// searches every position and returns the total nodes, report gets the index, depth, nodes and
+// best move of each position
+fn bench<F: FnMut(usize, u32, u64, &str)>(
+    depth: Option<u32>,
+    eval_data: &EvalData,
+    priority_eval_data: &PriorityEvalData,
+    mut report: F,
+) -> Result<u64, Box<dyn Error>> {
+    let stop = AtomicBool::new(false);
+    let control = SearchControl {
+        stop: &stop,
+        deadline: None,
+    };
+    let mut total_nodes = 0;
+
+    for (i, (position, position_depth)) in POSITIONS.iter().enumerate() {
+        let position: Board = position.parse()?;
+        let config = MinimaxConfig {
+            depth: depth.unwrap_or(*position_depth),
+            ..MinimaxConfig::default()
+        };
+
+        let mut nodes = 0;
+        let result = iterative_deepening(
+            &board::Board::new(&position),
+            &config,
+            eval_data,
+            priority_eval_data,
+            None,
+            &control,
+            |info| nodes = info.nodes(),
+        )?;
+        total_nodes += nodes;
+
+        let octi_move = match result.octi_move() {
+            Some(octi_move) => octi_move.to_string(),
+            None => "none".to_string(),
+        };
+        report(i, config.depth, nodes, &octi_move);
+    }
+
+    Ok(total_nodes)
+}
+
+// positions from engine games after a few random moves, with both teams to move, the depths keep
+// the whole bench to a few seconds
+const POSITIONS: [(&str, u32); 8] = [
+    ("6/1GGGG1/6/6/6/1RRRR1/6 r 12 12", 5),
+    ("6/1GGG[46]G1/6/6/4R[12]1/1RR1R1/6 g 10 10", 5),
+    ("6/1GG[47]G[02]G1/6/6/6/1RR[4]1RR[04]/6 r 9 8", 5),
+    (
+        "6/1G[06]G[7]1G[5]1/3G[6]2/6/6/1R[7]R[4]1R[02]R[04]/6 g 6 7",
+        5,
+    ),
+    (
+        "6/1G[16]G[46]G[06]G[06]1/6/6/4R[1]1/1R[013]R[4]1R[25]1/6 r 5 4",
+        5,
+    ),
+    (
+        "6/1G[06]G[46]2G[0456]/6/6/4R[12]1/1R[0123]R[124]1R[2]1/6 g 2 4",
+        5,
+    ),
+    (
+        "6/1G[067]G[47]1G[56]1/3G[0236]2/6/6/1R[012]R[1234]1R[2]R[034]/6 r 1 1",
+        6,
+    ),
+    (
+        "6/2G[4567]2G[0456]/2G[067]3/5R[12]/2R[0123]3/2R[1234]1R[23]1/6 g 0 1",
+        6,
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the searches have no deadline and the tables are keyed by whole positions, so the count is
+    // the same on every run until the search, the evaluation or the move generation changes
+    #[test]
+    fn fixed_node_count() {
+        let nodes = bench(
+            Some(3),
+            &EvalData::default(),
+            &PriorityEvalData::default(),
+            |_, _, _, _| {},
+        )
+        .unwrap();
+        assert_eq!(nodes, 32189);
+    }
+}
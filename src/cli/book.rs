@@ -0,0 +1,65 @@
+use std::error::Error;
+
+use super::super::ai::book::{Book, BookSearchConfig};
+use super::super::ai::eval::EvalData;
+use super::super::ai::priority::PriorityEvalData;
+use super::super::board::Board;
+use super::super::game::record::{read_records, GameResult};
+use super::Flags;
+
+// oxidized_octi book --out <path> [--games <path>] [--depth <n>] [--plies <n>] [--width <n>]
+//                    [--margin <n>] [--eval <path>] [--priority <path>]
+//
+// books the moves of the game records if --games is given, and searches the default board if
+// --depth is given (both can be combined, game moves are booked first)
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = Flags::parse(
+        args,
+        &[
+            "out", "games", "depth", "plies", "width", "margin", "eval", "priority",
+        ],
+    )?;
+
+    let out = flags.value("out").ok_or("Missing --out")?;
+    let depth: Option<u32> = flags.get("depth")?;
+    if flags.value("games").is_none() && depth.is_none() {
+        Err("Missing --games or --depth")?;
+    }
+    let plies = flags.get("plies")?.unwrap_or(6);
+
+    let mut book = Book::new();
+
+    if let Some(games) = flags.value("games") {
+        for (i, record) in read_records(games)?.iter().enumerate() {
+            let winner = match record.result() {
+                GameResult::Win(team) => Some(team),
+                _ => None,
+            };
+            book.add_game(record.moves(), winner, plies)
+                .map_err(|e| format!("Invalid game {}: {}", i, e))?;
+        }
+    }
+
+    if let Some(depth) = depth {
+        let eval_data = match flags.value("eval") {
+            Some(path) => EvalData::from_file(path)?,
+            None => EvalData::default(),
+        };
+        let priority_eval_data = match flags.value("priority") {
+            Some(path) => PriorityEvalData::from_file(path)?,
+            None => PriorityEvalData::default(),
+        };
+        let config = BookSearchConfig {
+            plies,
+            depth,
+            width: flags.get("width")?.unwrap_or(3),
+            margin: flags.get("margin")?.unwrap_or(50),
+        };
+
+        book.add_searched(&Board::default(), &config, &eval_data, &priority_eval_data)?;
+    }
+
+    book.write(out)?;
+    println!("wrote {} positions to {}", book.position_count(), out);
+    Ok(())
+}
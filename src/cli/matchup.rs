@@ -0,0 +1,102 @@
+use std::error::Error;
+use std::time::Duration;
+
+use super::super::ai::eval::EvalData;
+use super::super::ai::priority::PriorityEvalData;
+use super::super::ai::MinimaxConfig;
+use super::super::game::matchup::{play_match, MatchConfig};
+use super::Flags;
+
+// oxidized_octi match [--first <features>] [--second <features>] [--depth <n>] [--time <ms>]
+//                     [--openings <n>] [--random-plies <n>] [--max-plies <n>] [--seed <n>]
+//                     [--eval <path>] [--priority <path>]
+//
+// plays two configurations of the search against each other, features are a comma separated
+// list of pvs, null-move, lmr and aspiration, or none. The first engine defaults to pvs and the
+// second to none, with a time per move both search up to depth 64 unless a depth is given
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = Flags::parse(
+        args,
+        &[
+            "first",
+            "second",
+            "depth",
+            "time",
+            "openings",
+            "random-plies",
+            "max-plies",
+            "seed",
+            "eval",
+            "priority",
+        ],
+    )?;
+
+    let move_time = flags.get("time")?.map(Duration::from_millis);
+    let depth = flags
+        .get("depth")?
+        .unwrap_or(if move_time.is_some() { 64 } else { 4 });
+    if depth == 0 {
+        Err("--depth cannot be 0")?;
+    }
+    let first = engine(flags.value("first").unwrap_or("pvs"), depth)?;
+    let second = engine(flags.value("second").unwrap_or("none"), depth)?;
+    let config = MatchConfig {
+        openings: flags.get("openings")?.unwrap_or(50),
+        random_plies: flags.get("random-plies")?.unwrap_or(4),
+        max_plies: flags.get("max-plies")?.unwrap_or(200),
+        move_time,
+        seed: flags.get("seed")?.unwrap_or(0),
+    };
+
+    let eval_data = match flags.value("eval") {
+        Some(path) => EvalData::from_file(path)?,
+        None => EvalData::default(),
+    };
+    let priority_eval_data = match flags.value("priority") {
+        Some(path) => PriorityEvalData::from_file(path)?,
+        None => PriorityEvalData::default(),
+    };
+
+    let result = play_match(
+        &first,
+        &second,
+        &config,
+        &eval_data,
+        &priority_eval_data,
+        |result| {
+            println!(
+                "{}/{} games: {}",
+                result.games(),
+                2 * config.openings,
+                result
+            )
+        },
+    )?;
+    println!("First engine: {}", result);
+    Ok(())
+}
+
+fn engine(features: &str, depth: u32) -> Result<MinimaxConfig, String> {
+    let mut config = MinimaxConfig {
+        depth,
+        pvs: false,
+        null_move: false,
+        late_move_reductions: false,
+        aspiration: false,
+        ..MinimaxConfig::default()
+    };
+    if features == "none" {
+        return Ok(config);
+    }
+
+    for feature in features.split(',') {
+        match feature {
+            "pvs" => config.pvs = true,
+            "null-move" => config.null_move = true,
+            "lmr" => config.late_move_reductions = true,
+            "aspiration" => config.aspiration = true,
+            _ => Err(format!("Unrecognized feature: {}", feature))?,
+        }
+    }
+    Ok(config)
+}
@@ -0,0 +1,117 @@
+mod analyze;
+mod bench;
+mod book;
+mod matchup;
+mod perft;
+mod protocol;
+mod selfplay;
+mod solve;
+mod tablebase;
+mod train;
+mod tune;
+mod variant;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+
+use super::ai::book::Book;
+use super::ai::eval::EvalData;
+use super::ai::network::Network;
+use super::ai::priority::PriorityEvalData;
+use super::ai::tablebase::Tablebase;
+use super::game::Game;
+use super::ui;
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match args.first().map(|x| x.as_str()) {
+        Some("analyze") => analyze::run(&args[1..]),
+        Some("bench") => bench::run(&args[1..]),
+        Some("book") => book::run(&args[1..]),
+        Some("match") => matchup::run(&args[1..]),
+        Some("perft") => perft::run(&args[1..]),
+        Some("protocol") => protocol::run(&args[1..]),
+        Some("selfplay") => selfplay::run(&args[1..]),
+        Some("solve") => solve::run(&args[1..]),
+        Some("tablebase") => tablebase::run(&args[1..]),
+        Some("train") => train::run(&args[1..]),
+        Some("tune") => tune::run(&args[1..]),
+        Some("variant") => variant::run(&args[1..]),
+        _ => play(args),
+    }
+}
+
+// oxidized_octi [--eval <path>] [--network <path>] [--priority <path>] [--book <path>]
+//               [--tablebase <path>] [--seed <n>]
+//
+// the ai evaluates with the network instead of the eval data if one is given
+fn play(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = Flags::parse(
+        args,
+        &["eval", "network", "priority", "book", "tablebase", "seed"],
+    )?;
+
+    let mut game = Game::default();
+    if let Some(path) = flags.value("eval") {
+        game.set_eval_data(EvalData::from_file(path)?);
+    }
+    if let Some(path) = flags.value("network") {
+        game.set_network(Network::from_file(path)?);
+    }
+    if let Some(path) = flags.value("priority") {
+        game.set_priority_eval_data(PriorityEvalData::from_file(path)?);
+    }
+    if let Some(path) = flags.value("book") {
+        game.set_book(Book::from_file(path)?);
+    }
+    if let Some(path) = flags.value("tablebase") {
+        game.set_tablebase(Tablebase::from_file(path)?);
+    }
+    if let Some(seed) = flags.get("seed")? {
+        game.set_seed(seed);
+    }
+
+    ui::run(game)?;
+    Ok(())
+}
+
+// `--name value` pairs, only the names given to parse are accepted
+pub struct Flags(HashMap<String, String>);
+
+impl Flags {
+    pub fn parse(args: &[String], names: &[&str]) -> Result<Flags, String> {
+        let mut flags = HashMap::new();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("Unexpected argument: {}", arg))?;
+            if !names.contains(&name) {
+                Err(format!("Unrecognized flag: {}", arg))?;
+            }
+
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for flag: {}", arg))?;
+            flags.insert(String::from(name), value.clone());
+        }
+
+        Ok(Flags(flags))
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(|x| x.as_str())
+    }
+
+    pub fn get<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.value(name) {
+            Some(value) => Ok(Some(
+                value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for --{}: {}", name, value))?,
+            )),
+            None => Ok(None),
+        }
+    }
+}
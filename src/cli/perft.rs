@@ -0,0 +1,43 @@
+use std::error::Error;
+use std::time::Instant;
+
+use super::super::ai::board::{self, check_symmetries};
+use super::super::ai::moveiter::{compare_generators, perft};
+use super::super::board::Board;
+use super::Flags;
+
+// oxidized_octi perft [--depth <n>] [--position <position>]
+//
+// checks that the incremental move generator yields the same moves as the legacy one on every
+// position up to depth, and that the board symmetries map each of them to an equivalent position,
+// then times both generators
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = Flags::parse(args, &["depth", "position"])?;
+
+    let depth = flags.get("depth")?.unwrap_or(3);
+    let position: Board = match flags.value("position") {
+        Some(position) => position.parse()?,
+        None => Board::default(),
+    };
+    let board = board::Board::new(&position);
+
+    compare_generators(&board, depth)?;
+    println!("Generators agree up to depth {}", depth);
+    check_symmetries(&board, depth)?;
+    println!("Symmetries hold up to depth {}", depth);
+
+    for (name, legacy) in [("incremental", false), ("legacy", true)] {
+        let start = Instant::now();
+        let nodes = perft(&board, depth, legacy);
+        let elapsed = start.elapsed();
+        println!(
+            "{}: {} nodes in {:.3}s ({:.0} nodes/s)",
+            name,
+            nodes,
+            elapsed.as_secs_f64(),
+            nodes as f64 / elapsed.as_secs_f64()
+        );
+    }
+
+    Ok(())
+}
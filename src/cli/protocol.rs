@@ -0,0 +1,404 @@
+use std::error::Error;
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::super::ai::board;
+use super::super::ai::eval::EvalData;
+use super::super::ai::priority::PriorityEvalData;
+use super::super::ai::tablebase::Tablebase;
+use super::super::ai::{iterative_deepening, MinimaxConfig, SearchControl, SearchInfo};
+use super::super::board::{Board, BoardEventProcessor, OctiMove};
+
+// oxidized_octi protocol
+//
+// a uci like line protocol on stdin and stdout, for guis and test harnesses:
+//
+// uci                                  answered with id, option and uciok lines
+// isready                              answered with readyok
+// setoption name <name> value <value>
+// ucinewgame
+// position startpos|<position> [moves <move>, <move>, ...]
+// go [depth <n>] [movetime <ms>] [infinite]
+// stop
+// quit
+//
+// go searches deeper and deeper in the background, up to the Depth option, the given depth or, with
+// only a movetime, until the time is up, answering with an info line per finished depth and a
+// bestmove line once it is done or stopped:
+//
+// info depth <n> score <value> [in <moves>] nodes <n> time <ms> pv <move>, <move>, ...
+// bestmove <move>|none
+//
+// moves are written like in the TUI and separated by ", " as they contain spaces, scores are
+// from red's perspective, a forced win is followed by the moves the winner needs to force it,
+// errors are reported with info string lines
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if !args.is_empty() {
+        Err(format!("Unexpected argument: {}", args[0]))?;
+    }
+
+    // the session and its searches answer through the printer, so their lines never interleave
+    let (output, lines) = mpsc::channel::<String>();
+    let printer = thread::spawn(move || {
+        for line in lines {
+            println!("{}", line);
+        }
+    });
+
+    let mut session = Session::new(output);
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "quit" {
+            session.stop();
+            break;
+        }
+
+        session.process_line(line);
+    }
+    session.stop();
+    drop(session);
+
+    printer.join().map_err(|_| "The printer panicked")?;
+    Ok(())
+}
+
+// depth of go infinite, it is only ever reached by stopping
+const MAX_DEPTH: u32 = 64;
+
+struct Options {
+    depth: u32,
+    dynamic_ordering: bool,
+    pvs: bool,
+    null_move: bool,
+    late_move_reductions: bool,
+    aspiration: bool,
+    eval_data: Arc<EvalData>,
+    priority_eval_data: Arc<PriorityEvalData>,
+    tablebase: Option<Arc<Tablebase>>,
+}
+
+struct Session {
+    position: Board,
+    options: Options,
+    stop: Arc<AtomicBool>,
+    search: Option<JoinHandle<()>>,
+    // a line per message, without the newline
+    output: Sender<String>,
+}
+
+impl Session {
+    fn new(output: Sender<String>) -> Session {
+        Session {
+            position: Board::default(),
+            options: Options {
+                depth: MinimaxConfig::default().depth,
+                dynamic_ordering: MinimaxConfig::default().dynamic_ordering,
+                pvs: MinimaxConfig::default().pvs,
+                null_move: MinimaxConfig::default().null_move,
+                late_move_reductions: MinimaxConfig::default().late_move_reductions,
+                aspiration: MinimaxConfig::default().aspiration,
+                eval_data: Arc::new(EvalData::default()),
+                priority_eval_data: Arc::new(PriorityEvalData::default()),
+                tablebase: None,
+            },
+            stop: Arc::new(AtomicBool::new(false)),
+            search: None,
+            output,
+        }
+    }
+
+    // processes a line, errors are answered with an info string line
+    fn process_line(&mut self, line: &str) {
+        if let Err(e) = self.process(line) {
+            self.send(format!("info string {}", e));
+        }
+    }
+
+    fn process(&mut self, line: &str) -> Result<(), Box<dyn Error>> {
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+
+        match command {
+            "uci" => {
+                self.send(String::from("id name oxidized_octi"));
+                self.send(format!(
+                    "option name Depth type spin default {} min 1 max {}",
+                    MinimaxConfig::default().depth,
+                    MAX_DEPTH
+                ));
+                let config = MinimaxConfig::default();
+                for (name, default) in [
+                    ("DynamicOrdering", config.dynamic_ordering),
+                    ("PVS", config.pvs),
+                    ("NullMove", config.null_move),
+                    ("LateMoveReductions", config.late_move_reductions),
+                    ("Aspiration", config.aspiration),
+                ] {
+                    self.send(format!(
+                        "option name {} type check default {}",
+                        name, default
+                    ));
+                }
+                for name in ["Eval", "Priority", "Tablebase"] {
+                    self.send(format!("option name {} type string default <empty>", name));
+                }
+                self.send(String::from("uciok"));
+            }
+            "isready" => self.send(String::from("readyok")),
+            "ucinewgame" => {
+                self.stop();
+                self.position = Board::default();
+            }
+            "setoption" => {
+                self.stop();
+                self.set_option(args)?;
+            }
+            "position" => {
+                self.stop();
+                self.position = parse_position(args)?;
+            }
+            "go" => {
+                self.stop();
+                self.go(args)?;
+            }
+            "stop" => self.stop(),
+            _ => Err(format!("Unrecognized command: {}", command))?,
+        }
+
+        Ok(())
+    }
+
+    // setoption name <name> value <value>, paths may contain spaces
+    fn set_option(&mut self, args: &str) -> Result<(), Box<dyn Error>> {
+        let args = args.strip_prefix("name ").ok_or("Expected name")?;
+        let (name, value) = args.split_once(" value ").ok_or("Expected value")?;
+
+        match name {
+            "Depth" => {
+                self.options.depth = match value.parse() {
+                    Ok(depth) if (1..=MAX_DEPTH).contains(&depth) => depth,
+                    _ => Err(format!("Invalid depth: {}", value))?,
+                }
+            }
+            "DynamicOrdering" => self.options.dynamic_ordering = parse_check(value)?,
+            "PVS" => self.options.pvs = parse_check(value)?,
+            "NullMove" => self.options.null_move = parse_check(value)?,
+            "LateMoveReductions" => self.options.late_move_reductions = parse_check(value)?,
+            "Aspiration" => self.options.aspiration = parse_check(value)?,
+            "Eval" => self.options.eval_data = Arc::new(EvalData::from_file(value)?),
+            "Priority" => {
+                self.options.priority_eval_data = Arc::new(PriorityEvalData::from_file(value)?)
+            }
+            "Tablebase" => self.options.tablebase = Some(Arc::new(Tablebase::from_file(value)?)),
+            _ => Err(format!("Unrecognized option: {}", name))?,
+        }
+
+        Ok(())
+    }
+
+    fn go(&mut self, args: &str) -> Result<(), Box<dyn Error>> {
+        let mut depth = None;
+        let mut deadline = None;
+
+        let args: Vec<&str> = args.split_whitespace().collect();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match *arg {
+                "depth" => {
+                    let value = args.next().ok_or("Missing depth")?;
+                    depth = match value.parse() {
+                        Ok(depth) if (1..=MAX_DEPTH).contains(&depth) => Some(depth),
+                        _ => Err(format!("Invalid depth: {}", value))?,
+                    }
+                }
+                "movetime" => {
+                    let value = args.next().ok_or("Missing movetime")?;
+                    let ms = value
+                        .parse()
+                        .map_err(|_| format!("Invalid movetime: {}", value))?;
+                    deadline = Some(Instant::now() + Duration::from_millis(ms));
+                }
+                "infinite" => depth = Some(MAX_DEPTH),
+                _ => Err(format!("Unrecognized go argument: {}", arg))?,
+            }
+        }
+        // a movetime alone searches until the time is up
+        let depth = depth.unwrap_or(match deadline {
+            Some(_) => MAX_DEPTH,
+            None => self.options.depth,
+        });
+
+        let board = board::Board::new(&self.position);
+        let config = MinimaxConfig {
+            depth,
+            dynamic_ordering: self.options.dynamic_ordering,
+            pvs: self.options.pvs,
+            null_move: self.options.null_move,
+            late_move_reductions: self.options.late_move_reductions,
+            aspiration: self.options.aspiration,
+        };
+        let eval_data = self.options.eval_data.clone();
+        let priority_eval_data = self.options.priority_eval_data.clone();
+        let tablebase = self.options.tablebase.clone();
+        let stop = self.stop.clone();
+        let output = self.output.clone();
+
+        self.search = Some(thread::spawn(move || {
+            let send = |line: String| {
+                let _ = output.send(line);
+            };
+            let result = iterative_deepening(
+                &board,
+                &config,
+                eval_data.as_ref(),
+                priority_eval_data.as_ref(),
+                tablebase.as_deref(),
+                &SearchControl {
+                    stop: &stop,
+                    deadline,
+                },
+                |info| send(info_line(info)),
+            );
+
+            match result {
+                Ok(result) => match result.octi_move() {
+                    Some(octi_move) => send(format!("bestmove {}", octi_move)),
+                    None => send(String::from("bestmove none")),
+                },
+                Err(e) => {
+                    send(format!("info string {}", e));
+                    send(String::from("bestmove none"));
+                }
+            }
+        }));
+
+        Ok(())
+    }
+
+    // stops the running search, if any, and waits for its bestmove, a search that panicked never
+    // sent one, so it is sent here and the session goes on
+    fn stop(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.stop.store(true, Ordering::Relaxed);
+            if search.join().is_err() {
+                self.send(String::from("info string The search panicked"));
+                self.send(String::from("bestmove none"));
+            }
+            self.stop.store(false, Ordering::Relaxed);
+        }
+    }
+
+    // the printer only stops listening once the session is dropped
+    fn send(&self, line: String) {
+        let _ = self.output.send(line);
+    }
+}
+
+fn info_line(info: &SearchInfo) -> String {
+    let score = match info.score().moves_to_win() {
+        Some(moves) => format!("{} in {}", info.score().value(), moves),
+        None => info.score().value().to_string(),
+    };
+    format!(
+        "info depth {} score {} nodes {} time {} pv {}",
+        info.depth(),
+        score,
+        info.nodes(),
+        info.elapsed().as_millis(),
+        join_moves(info.pv())
+    )
+}
+
+fn parse_check(value: &str) -> Result<bool, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid check value: {}", value))
+}
+
+// startpos|<position> [moves <move>, <move>, ...]
+fn parse_position(args: &str) -> Result<Board, Box<dyn Error>> {
+    let (position, moves) = match args.split_once(" moves ") {
+        Some((position, moves)) => (position, Some(moves)),
+        None => (args, None),
+    };
+
+    let mut board = match position.trim() {
+        "startpos" => Board::default(),
+        position => position.parse()?,
+    };
+    if let Some(moves) = moves {
+        // positions like (1,5) have a comma too, but no space after it
+        for octi_move in moves.split(", ") {
+            let octi_move: OctiMove = octi_move.trim().parse()?;
+            board
+                .make_move(&octi_move)
+                .map_err(|e| format!("Illegal move {}: {}", octi_move, e))?;
+        }
+    }
+
+    Ok(board)
+}
+
+fn join_moves(octi_moves: &[OctiMove]) -> String {
+    octi_moves
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn position_applies_moves() {
+        let expected = "6/1GG[4]GG1/6/6/6/1RRR[0]R1/6 r 11 11";
+        for args in [
+            "startpos moves arr (3,5) 0, arr (2,1) 4",
+            "6/1GGGG1/6/6/6/1RRRR1/6 r 12 12 moves arr (3,5) 0, arr (2,1) 4",
+        ] {
+            assert_eq!(parse_position(args).unwrap().to_string(), expected);
+        }
+
+        // green's octi on red's turn
+        assert!(parse_position("startpos moves arr (2,1) 4").is_err());
+    }
+
+    #[test]
+    fn go_ends_with_one_bestmove() {
+        let (output, lines) = mpsc::channel();
+        let mut session = Session::new(output);
+        session.process_line("position startpos");
+        session.process_line("go depth 2");
+        session.search.take().unwrap().join().unwrap();
+
+        let lines = lines.try_iter().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("info depth 1 "));
+        assert!(lines[1].starts_with("info depth 2 "));
+        assert!(lines[2].starts_with("bestmove arr "));
+    }
+
+    #[test]
+    fn stopped_searches_send_one_bestmove() {
+        let (output, lines) = mpsc::channel();
+        let mut session = Session::new(output);
+        // every go stops the search before it
+        for _ in 0..3 {
+            session.process_line("go infinite");
+        }
+        session.process_line("stop");
+        session.process_line("stop");
+
+        let bestmoves = lines.try_iter().filter(|x| x.starts_with("bestmove "));
+        assert_eq!(bestmoves.count(), 3);
+    }
+}
@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::path::Path;
+
+use super::super::ai::eval::EvalData;
+use super::super::ai::priority::PriorityEvalData;
+use super::super::game::selfplay::{generate, SelfPlayConfig};
+use super::Flags;
+
+// oxidized_octi selfplay --out <path> [--games <n>] [--depth <n>] [--random-plies <n>]
+//                        [--max-plies <n>] [--threads <n>] [--seed <n>] [--eval <path>]
+//                        [--priority <path>]
+//
+// running it again with the same out file resumes where it stopped
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = Flags::parse(
+        args,
+        &[
+            "out",
+            "games",
+            "depth",
+            "random-plies",
+            "max-plies",
+            "threads",
+            "seed",
+            "eval",
+            "priority",
+        ],
+    )?;
+
+    let out = flags.value("out").ok_or("Missing --out")?;
+    let config = SelfPlayConfig {
+        games: flags.get("games")?.unwrap_or(100),
+        depth: flags.get("depth")?.unwrap_or(2),
+        random_plies: flags.get("random-plies")?.unwrap_or(4),
+        max_plies: flags.get("max-plies")?.unwrap_or(200),
+        threads: flags.get("threads")?.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|x| x.get())
+                .unwrap_or(1)
+        }),
+        seed: flags.get("seed")?.unwrap_or(0),
+    };
+    if config.depth == 0 {
+        Err("--depth cannot be 0")?;
+    }
+
+    let eval_data = match flags.value("eval") {
+        Some(path) => EvalData::from_file(path)?,
+        None => EvalData::default(),
+    };
+    let priority_eval_data = match flags.value("priority") {
+        Some(path) => PriorityEvalData::from_file(path)?,
+        None => PriorityEvalData::default(),
+    };
+
+    generate(
+        Path::new(out),
+        &config,
+        &eval_data,
+        &priority_eval_data,
+        |done, total| println!("{}/{} games", done, total),
+    )
+}
@@ -0,0 +1,50 @@
+use std::error::Error;
+use std::time::Instant;
+
+use super::super::ai::board;
+use super::super::ai::solve::{solve, Outcome, SolveConfig};
+use super::super::board::{Board, Boardable};
+use super::Flags;
+
+// oxidized_octi solve [--position <position>] [--nodes <n>]
+//
+// proves the position won or lost for the side to move with a proof number search, and prints the
+// line of the proof
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = Flags::parse(args, &["position", "nodes"])?;
+
+    let config = SolveConfig {
+        max_nodes: flags.get("nodes")?.unwrap_or(1_000_000),
+    };
+    let position: Board = match flags.value("position") {
+        Some(position) => position.parse()?,
+        None => Board::default(),
+    };
+    let board = board::Board::new(&position);
+
+    let start = Instant::now();
+    let solution = solve(&board, &config);
+    let elapsed = start.elapsed();
+
+    let team = position.turn();
+    match solution.outcome() {
+        Outcome::Won => println!("{:?} to move wins", team),
+        Outcome::Lost => println!("{:?} to move loses", team),
+        Outcome::Unknown => println!("No proof within {} nodes", config.max_nodes),
+    }
+    println!(
+        "{} nodes in {:.3}s",
+        solution.nodes(),
+        elapsed.as_secs_f64()
+    );
+    if !solution.line().is_empty() {
+        let line = solution
+            .line()
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        println!("line: {}", line.join(", "));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use std::error::Error;
+
+use super::super::ai::tablebase::{Material, Tablebase};
+use super::Flags;
+
+// oxidized_octi tablebase --out <path> --red <n> --green <n> --red-arrows <n> --green-arrows <n>
+//                         [--threads <n>]
+//
+// arrows are the total arrows of a team, on its octis and in its reserve, every material the
+// given one can capture its way into is generated as well
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = Flags::parse(
+        args,
+        &[
+            "out",
+            "red",
+            "green",
+            "red-arrows",
+            "green-arrows",
+            "threads",
+        ],
+    )?;
+
+    let out = flags.value("out").ok_or("Missing --out")?;
+    let material = Material::new(
+        flags.get("red")?.ok_or("Missing --red")?,
+        flags.get("green")?.ok_or("Missing --green")?,
+        flags.get("red-arrows")?.ok_or("Missing --red-arrows")?,
+        flags.get("green-arrows")?.ok_or("Missing --green-arrows")?,
+    );
+    let threads = flags.get("threads")?.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|x| x.get())
+            .unwrap_or(1)
+    });
+
+    let tablebase = Tablebase::generate(material, threads, |material, size| {
+        println!("Solving {}: {} positions", material, size)
+    })?;
+    tablebase.write(out)?;
+    println!("Wrote {} tables to {}", tablebase.materials().len(), out);
+    Ok(())
+}
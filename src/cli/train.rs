@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+
+use super::super::ai::network::{train, TrainConfig};
+use super::tune::read_training_positions;
+use super::Flags;
+
+// oxidized_octi train [--games <path>] [--positions <path>] [--out <path>] [--hidden <n>]
+//                     [--epochs <n>] [--batch <n>] [--rate <r>] [--scale <s>] [--seed <n>]
+//                     [--skip-plies <n>]
+//
+// trains a network evaluator on the same files as tune, play loads it with --network
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = Flags::parse(
+        args,
+        &[
+            "games",
+            "positions",
+            "out",
+            "hidden",
+            "epochs",
+            "batch",
+            "rate",
+            "scale",
+            "seed",
+            "skip-plies",
+        ],
+    )?;
+
+    let out = flags.value("out").unwrap_or("network.json");
+    let config = TrainConfig {
+        hidden: flags.get("hidden")?.unwrap_or(32),
+        epochs: flags.get("epochs")?.unwrap_or(30),
+        batch_size: flags.get("batch")?.unwrap_or(256),
+        learning_rate: flags.get("rate")?.unwrap_or(0.001),
+        // about what tune fits for the default eval data, so both score in similar points
+        scale: flags.get("scale")?.unwrap_or(400.0),
+        seed: flags.get("seed")?.unwrap_or(0),
+    };
+
+    let positions = read_training_positions(&flags)?;
+    println!("{} positions", positions.len());
+
+    let network = train(&positions, &config, |epoch, error, held_out_error| {
+        println!(
+            "epoch {}: error {:.6}, held out {:.6}",
+            epoch, error, held_out_error
+        );
+    })?;
+
+    serde_json::to_writer(BufWriter::new(File::create(out)?), &network)?;
+    println!("wrote {}", out);
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+
+use super::super::ai::board::Board;
+use super::super::ai::eval::EvalData;
+use super::super::ai::tune::{tune, TuneConfig};
+use super::super::board::Team;
+use super::super::game::record::{read_records, GameResult};
+use super::super::game::selfplay::read_positions;
+use super::Flags;
+
+// oxidized_octi tune [--games <path>] [--positions <path>] [--eval <path>] [--out <path>]
+//                    [--epochs <n>] [--rate <r>] [--skip-plies <n>]
+//
+// games are game record files, positions are self-play files
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = Flags::parse(
+        args,
+        &[
+            "games",
+            "positions",
+            "eval",
+            "out",
+            "epochs",
+            "rate",
+            "skip-plies",
+        ],
+    )?;
+
+    let base = match flags.value("eval") {
+        Some(path) => EvalData::from_file(path)?,
+        None => EvalData::default(),
+    };
+    let out = flags.value("out").unwrap_or("tuned_eval_data.json");
+    let config = TuneConfig {
+        epochs: flags.get("epochs")?.unwrap_or(500),
+        learning_rate: flags.get("rate")?.unwrap_or(1.0),
+    };
+
+    let positions = read_training_positions(&flags)?;
+    println!("{} positions", positions.len());
+
+    let result = tune(&positions, &base, &config, |epoch, error| {
+        if epoch % 50 == 0 {
+            println!("epoch {}: error {:.6}", epoch, error);
+        }
+    })?;
+
+    println!("scale {:.1}", result.scale);
+    println!(
+        "error {:.6} -> {:.6}",
+        result.error_before, result.error_after
+    );
+
+    serde_json::to_writer_pretty(BufWriter::new(File::create(out)?), &result.eval_data)?;
+    println!("wrote {}", out);
+    Ok(())
+}
+
+// the positions of the --games and --positions files with the results of their games, from red's
+// perspective, after --skip-plies plies
+pub fn read_training_positions(flags: &Flags) -> Result<Vec<(Board, f64)>, Box<dyn Error>> {
+    if flags.value("games").is_none() && flags.value("positions").is_none() {
+        Err("Missing --games or --positions")?;
+    }
+    // the opening is mostly noise for the final result
+    let skip_plies = flags.get("skip-plies")?.unwrap_or(4);
+
+    let mut positions = Vec::new();
+    if let Some(games) = flags.value("games") {
+        for (i, record) in read_records(games)?.iter().enumerate() {
+            let Some(result) = red_result(record.result()) else {
+                continue;
+            };
+
+            let boards = record
+                .positions()
+                .map_err(|e| format!("Invalid game {}: {}", i, e))?;
+            positions.extend(
+                boards
+                    .iter()
+                    .skip(skip_plies)
+                    .map(|x| (Board::new(x), result)),
+            );
+        }
+    }
+    if let Some(path) = flags.value("positions") {
+        for record in read_positions(path)? {
+            match red_result(record.result()) {
+                Some(result) if record.ply() >= skip_plies => {
+                    positions.push((Board::new(record.position()), result))
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(positions)
+}
+
+fn red_result(result: GameResult) -> Option<f64> {
+    match result {
+        GameResult::Win(Team::Red) => Some(1.0),
+        GameResult::Win(Team::Green) => Some(0.0),
+        GameResult::Draw => Some(0.5),
+        GameResult::Unfinished => None,
+    }
+}
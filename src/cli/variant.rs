@@ -0,0 +1,147 @@
+use std::error::Error;
+use std::time::Instant;
+
+use super::super::ai::board::{self, UndoStack};
+use super::super::ai::eval::EvalData;
+use super::super::ai::priority::PriorityEvalData;
+use super::super::ai::tablebase::TbValue;
+use super::super::ai::variant::{Variant, VariantConfig};
+use super::super::ai::{minimax, winner, MinimaxConfig};
+use super::super::board::{Board, Boardable};
+use super::Flags;
+
+// oxidized_octi variant [--position <position>] [--in <path>] [--out <path>]
+//                       [--max-positions <n>] [--check-depth <n>] [--check-positions <n>]
+//                       [--eval <path>]
+//
+// solves every position reachable from the start of a reduced board, or loads a solved variant,
+// and prints the value of the start with its line. The default start is a 4x5 board with two
+// octis and two arrows a team
+//
+// with a check depth minimax plays a sample of the won and drawn positions, a move is right if it
+// keeps the value
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = Flags::parse(
+        args,
+        &[
+            "position",
+            "in",
+            "out",
+            "max-positions",
+            "check-depth",
+            "check-positions",
+            "eval",
+        ],
+    )?;
+
+    let variant = match flags.value("in") {
+        Some(path) => Variant::from_file(path)?,
+        None => {
+            let position: Board = flags
+                .value("position")
+                .unwrap_or("4/1GG1/4/1RR1/4 r 2 2")
+                .parse()?;
+            let config = VariantConfig {
+                max_positions: flags.get("max-positions")?.unwrap_or(5_000_000),
+            };
+
+            let start = Instant::now();
+            let variant = Variant::solve(&board::Board::new(&position), &config, |positions| {
+                println!("{} positions enumerated", positions)
+            })?;
+            println!(
+                "Solved {} positions in {:.3}s",
+                variant.size(),
+                start.elapsed().as_secs_f64()
+            );
+            variant
+        }
+    };
+    if let Some(path) = flags.value("out") {
+        variant.write(path)?;
+        println!("Wrote {}", path);
+    }
+
+    let start = variant.start();
+    let value = variant.probe(&start).ok_or("The start is not solved")?;
+    println!("{:?} to move: {}", start.turn(), value);
+    if let TbValue::Win(plies) | TbValue::Loss(plies) = value {
+        let mut board = start;
+        let mut line = Vec::new();
+        for _ in 0..plies {
+            let (octi_move, _) = variant.best_move(&board).ok_or("Broken line")?;
+            board.do_move(&octi_move, &mut UndoStack::new());
+            line.push(octi_move.to_string());
+        }
+        println!("line: {}", line.join(", "));
+    }
+
+    if let Some(depth) = flags.get("check-depth")? {
+        let eval_data = match flags.value("eval") {
+            Some(path) => EvalData::from_file(path)?,
+            None => EvalData::default(),
+        };
+        check(
+            &variant,
+            depth,
+            flags.get("check-positions")?.unwrap_or(1000),
+            &eval_data,
+        )?;
+    }
+
+    Ok(())
+}
+
+// compares the moves of a minimax search to the solution on up to count positions spread over
+// the undecided won and drawn ones
+fn check(
+    variant: &Variant,
+    depth: u32,
+    count: usize,
+    eval_data: &EvalData,
+) -> Result<(), Box<dyn Error>> {
+    let positions = variant
+        .positions()
+        .into_iter()
+        .filter(|(board, value)| winner(board).is_none() && !matches!(value, TbValue::Loss(_)))
+        .collect::<Vec<_>>();
+    let step = (positions.len() / count.max(1)).max(1);
+
+    let config = MinimaxConfig {
+        depth,
+        ..MinimaxConfig::default()
+    };
+    let priority_eval_data = PriorityEvalData::default();
+    // right and total moves of won and drawn positions
+    let mut wins = (0, 0);
+    let mut draws = (0, 0);
+
+    for (board, value) in positions.iter().step_by(step).take(count) {
+        let result = minimax(board, &config, eval_data, &priority_eval_data, None)?;
+        let octi_move = match result.octi_move() {
+            Some(octi_move) => octi_move,
+            None => continue,
+        };
+
+        let mut next_board = *board;
+        next_board.do_move(&octi_move, &mut UndoStack::new());
+        let next_value = variant.probe(&next_board).ok_or("Unsolved position")?;
+        let (right, total) = match value {
+            TbValue::Win(_) => &mut wins,
+            _ => &mut draws,
+        };
+        *total += 1;
+        if match value {
+            TbValue::Win(_) => matches!(next_value, TbValue::Loss(_)),
+            _ => next_value == TbValue::Draw,
+        } {
+            *right += 1;
+        }
+    }
+
+    println!(
+        "Depth {}: kept {}/{} wins and {}/{} draws",
+        depth, wins.0, wins.1, draws.0, draws.1
+    );
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use super::super::ai::board;
+use super::super::ai::eval::{Evaluator, Value};
+use super::super::ai::priority::MoveOrderer;
+use super::super::ai::tablebase::Tablebase;
+use super::super::ai::{search, Engine, MinimaxConfig};
+use super::super::board::{Board, BoardEventProcessor, Boardable, OctiMove, Team};
+
+// in eval points from the perspective of the mover, how much a move has to lose to be flagged
+const INACCURACY: i32 = 50;
+const MISTAKE: i32 = 150;
+const BLUNDER: i32 = 400;
+// the position after a move is searched one ply shallower and searches need a depth
+const MIN_DEPTH: u32 = 2;
+
+// how long every position of a game is searched
+#[derive(Clone, Copy)]
+pub enum Limit {
+    Depth(u32),
+    // searches one depth deeper at a time, the depth running when the time is up is still
+    // finished
+    Time(Duration),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+// the search scores of the positions before and after a move, from red's perspective
+#[derive(Clone, Copy)]
+pub struct Annotation {
+    before: Value,
+    after: Value,
+    judgement: Option<Judgement>,
+}
+
+impl Annotation {
+    pub fn new(before: Value, after: Value, team: Team) -> Annotation {
+        Annotation {
+            before,
+            after,
+            judgement: judge(before, after, team),
+        }
+    }
+
+    // Getters
+
+    pub fn before(&self) -> Value {
+        self.before
+    }
+
+    pub fn after(&self) -> Value {
+        self.after
+    }
+
+    pub fn judgement(&self) -> Option<Judgement> {
+        self.judgement
+    }
+}
+
+// annotates every move of the game played from start
+//
+// the position after a move is searched one ply shallower than the position before it, so both
+// searches look at the same plies of the game and the parity of the depth doesn't swing the scores
+pub fn analyze<E: Evaluator, O: MoveOrderer>(
+    start: &Board,
+    moves: &[OctiMove],
+    limit: Limit,
+    evaluator: &E,
+    orderer: &O,
+    tablebase: Option<&Tablebase>,
+) -> Result<Vec<Annotation>, Box<dyn Error>> {
+    let search_depth = |position: &Board, depth| {
+        search(
+            &board::Board::new(position),
+            &Engine::Minimax(MinimaxConfig {
+                depth,
+                ..MinimaxConfig::default()
+            }),
+            evaluator,
+            orderer,
+            tablebase,
+        )
+        .map(|x| x.score().value())
+    };
+
+    let mut position = start.clone();
+    let mut annotations = Vec::with_capacity(moves.len());
+    for (ply, octi_move) in moves.iter().enumerate() {
+        let team = position.turn();
+        let (before, depth) = match limit {
+            Limit::Depth(depth) => (search_depth(&position, depth)?, depth),
+            Limit::Time(time) => {
+                let start = Instant::now();
+                let mut depth = MIN_DEPTH;
+                loop {
+                    let value = search_depth(&position, depth)?;
+                    // a decided position won't change with depth
+                    if start.elapsed() >= time || matches!(value, Value::Win(_)) {
+                        break (value, depth);
+                    }
+                    depth += 1;
+                }
+            }
+        };
+
+        position
+            .make_move(octi_move)
+            .map_err(|e| format!("Illegal move {} at ply {}: {}", octi_move, ply, e))?;
+        let after = search_depth(&position, depth - 1)?;
+        annotations.push(Annotation::new(before, after, team));
+    }
+
+    Ok(annotations)
+}
+
+// a move that gives up a won position or walks into a lost one is always a blunder
+fn judge(before: Value, after: Value, team: Team) -> Option<Judgement> {
+    let drop = match (before, after) {
+        (Value::Win(a), Value::Win(b)) if a == b => 0,
+        (Value::Win(winner), _) if winner == team => return Some(Judgement::Blunder),
+        (_, Value::Win(winner)) if winner != team => return Some(Judgement::Blunder),
+        (Value::Score(before), Value::Score(after)) => match team {
+            Team::Red => before - after,
+            Team::Green => after - before,
+        },
+        // the move is better than the search expected
+        _ => 0,
+    };
+
+    if drop >= BLUNDER {
+        Some(Judgement::Blunder)
+    } else if drop >= MISTAKE {
+        Some(Judgement::Mistake)
+    } else if drop >= INACCURACY {
+        Some(Judgement::Inaccuracy)
+    } else {
+        None
+    }
+}
+
+impl FromStr for Limit {
+    type Err = String;
+
+    // a depth, or a time per position like 2s or 500ms
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid limit: {}", s);
+        if let Some(ms) = s.strip_suffix("ms") {
+            Ok(Limit::Time(Duration::from_millis(
+                ms.parse().map_err(|_| invalid())?,
+            )))
+        } else if let Some(secs) = s.strip_suffix('s') {
+            Ok(Limit::Time(Duration::from_secs_f64(
+                secs.parse().map_err(|_| invalid())?,
+            )))
+        } else {
+            match s.parse().map_err(|_| invalid())? {
+                depth if depth < MIN_DEPTH => {
+                    Err(format!("Analysis needs at least depth {}", MIN_DEPTH))
+                }
+                depth => Ok(Limit::Depth(depth)),
+            }
+        }
+    }
+}
+
+impl Display for Judgement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Judgement::Inaccuracy => write!(f, "inaccuracy"),
+            Judgement::Mistake => write!(f, "mistake"),
+            Judgement::Blunder => write!(f, "blunder"),
+        }
+    }
+}
+
+impl FromStr for Judgement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inaccuracy" => Ok(Judgement::Inaccuracy),
+            "mistake" => Ok(Judgement::Mistake),
+            "blunder" => Ok(Judgement::Blunder),
+            _ => Err(format!("Unrecognized judgement: {}", s)),
+        }
+    }
+}
+
+// before after [judgement]
+impl Display for Annotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.before, self.after)?;
+        if let Some(judgement) = self.judgement {
+            write!(f, " {}", judgement)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Annotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let args: Vec<&str> = s.split(' ').collect();
+        if args.len() != 2 && args.len() != 3 {
+            Err(format!("Invalid number of arguments: {}", args.len()))?;
+        }
+
+        Ok(Annotation {
+            before: args[0].parse()?,
+            after: args[1].parse()?,
+            judgement: args.get(2).map(|x| x.parse()).transpose()?,
+        })
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use super::super::ai::board::Board;
+use super::super::ai::priority::PriorityEvalData;
+use super::super::ai::tablebase::Tablebase;
+use super::super::ai::{iterative_deepening, MinimaxConfig, SearchControl};
+use super::super::board::OctiMove;
+use super::analysis::Annotation;
+use super::GameEvaluator;
+
+// a search running on another thread, so the ui keeps drawing while it runs
+pub struct BackgroundSearch {
+    // the depth of an iterative deepening search, the other searches can't be stopped
+    depth: Option<u32>,
+    stop: Arc<AtomicBool>,
+    progress: Receiver<String>,
+    handle: JoinHandle<Result<Finished, String>>,
+}
+
+// what a background search ends with
+pub enum Finished {
+    // the moves the ai expects from the position, the first one is played
+    Line(Vec<OctiMove>),
+    // a message for the player, like the best moves of a hint
+    Message(String),
+    // one per move of the history
+    Annotations(Vec<Annotation>),
+}
+
+impl BackgroundSearch {
+    pub fn start(
+        board: Board,
+        config: MinimaxConfig,
+        evaluator: GameEvaluator,
+        priority_eval_data: PriorityEvalData,
+        tablebase: Option<Arc<Tablebase>>,
+    ) -> BackgroundSearch {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, progress) = mpsc::channel();
+
+        let depth = config.depth;
+        let thread_stop = stop.clone();
+        let handle = thread::spawn(move || {
+            let mut pv = vec![];
+            let result = iterative_deepening(
+                &board,
+                &config,
+                &evaluator,
+                &priority_eval_data,
+                tablebase.as_deref(),
+                &SearchControl {
+                    stop: &thread_stop,
+                    deadline: None,
+                },
+                |info| {
+                    pv = info.pv().to_vec();
+                    let best_move = match info.pv().first() {
+                        Some(octi_move) => octi_move.to_string(),
+                        None => String::from("none"),
+                    };
+                    // the ui may have stopped listening
+                    let _ = sender.send(format!(
+                        "Depth {}: {}, best move: {} ({} nodes)\nPress Esc to play it",
+                        info.depth(),
+                        info.score(),
+                        best_move,
+                        info.nodes()
+                    ));
+                },
+            )
+            .map_err(|e| e.to_string())?;
+
+            // the line is read from the score table, the move from the search
+            let line = match result.octi_move() {
+                Some(octi_move) if pv.first() != Some(&octi_move) => vec![octi_move],
+                Some(_) => pv,
+                None => vec![],
+            };
+            Ok(Finished::Line(line))
+        });
+
+        BackgroundSearch {
+            depth: Some(depth),
+            stop,
+            progress,
+            handle,
+        }
+    }
+
+    // runs any other search, which reports no progress and can't be stopped
+    pub fn run<F>(search: F) -> BackgroundSearch
+    where
+        F: FnOnce() -> Result<Finished, String> + Send + 'static,
+    {
+        let (_, progress) = mpsc::channel();
+
+        BackgroundSearch {
+            depth: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            progress,
+            handle: thread::spawn(search),
+        }
+    }
+
+    // Getters
+
+    pub fn depth(&self) -> Option<u32> {
+        self.depth
+    }
+
+    // whether abort makes the search finish early
+    pub fn stoppable(&self) -> bool {
+        self.depth.is_some()
+    }
+
+    // the progress of the deepest depth finished since the last call
+    pub fn progress(&self) -> Option<String> {
+        self.progress.try_iter().last()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    // Operations
+
+    // an iterative deepening search finishes with the best move of the deepest finished depth,
+    // the other searches don't notice
+    pub fn abort(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    // waits for the search to finish, the line of an iterative deepening search is the principal
+    // variation of the deepest finished depth, which is empty if there are no legal moves
+    pub fn join(self) -> Result<Finished, String> {
+        self.handle
+            .join()
+            .map_err(|_| String::from("Search thread panicked"))?
+    }
+}
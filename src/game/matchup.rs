@@ -0,0 +1,161 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use super::super::ai::board;
+use super::super::ai::eval::Evaluator;
+use super::super::ai::priority::MoveOrderer;
+use super::super::ai::{iterative_deepening, legal_moves, winner, MinimaxConfig, SearchControl};
+use super::super::board::{Board, BoardEventProcessor, Boardable, Team};
+use super::record::GameResult;
+
+pub struct MatchConfig {
+    // every opening is played twice, so each engine plays it as red and as green
+    pub openings: usize,
+    // the moves of an opening are picked uniformly at random
+    pub random_plies: usize,
+    // games still going after max_plies are called a draw
+    pub max_plies: usize,
+    // the engines search deeper until the time runs out or they reach their depth
+    pub move_time: Option<Duration>,
+    // opening i is played with seed + i
+    pub seed: u64,
+}
+
+// the results of the first engine
+#[derive(Clone, Copy, Default)]
+pub struct MatchResult {
+    wins: usize,
+    losses: usize,
+    draws: usize,
+}
+
+// plays the first engine against the second on the same evaluator, progress gets the results
+// after every game
+pub fn play_match<E, O, F>(
+    first: &MinimaxConfig,
+    second: &MinimaxConfig,
+    config: &MatchConfig,
+    evaluator: &E,
+    orderer: &O,
+    mut progress: F,
+) -> Result<MatchResult, Box<dyn Error>>
+where
+    E: Evaluator,
+    O: MoveOrderer,
+    F: FnMut(&MatchResult),
+{
+    let mut result = MatchResult::default();
+
+    for opening in 0..config.openings {
+        for first_team in [Team::Red, Team::Green] {
+            let engines = match first_team {
+                Team::Red => [first, second],
+                Team::Green => [second, first],
+            };
+            match play_game(opening, engines, config, evaluator, orderer)? {
+                GameResult::Win(team) if team == first_team => result.wins += 1,
+                GameResult::Win(_) => result.losses += 1,
+                _ => result.draws += 1,
+            }
+            progress(&result);
+        }
+    }
+
+    Ok(result)
+}
+
+// engines holds the red and the green engine
+fn play_game<E: Evaluator, O: MoveOrderer>(
+    opening: usize,
+    engines: [&MinimaxConfig; 2],
+    config: &MatchConfig,
+    evaluator: &E,
+    orderer: &O,
+) -> Result<GameResult, Box<dyn Error>> {
+    let mut rng = StdRng::seed_from_u64(config.seed.wrapping_add(opening as u64));
+    let mut state = Board::default();
+    let stop = AtomicBool::new(false);
+
+    for ply in 0.. {
+        let board = board::Board::new(&state);
+        if let Some(team) = winner(&board) {
+            return Ok(GameResult::Win(team));
+        }
+        if ply >= config.max_plies {
+            return Ok(GameResult::Draw);
+        }
+
+        let octi_move = if ply < config.random_plies {
+            legal_moves(&board).choose(&mut rng).cloned()
+        } else {
+            let engine = match board.turn() {
+                Team::Red => engines[0],
+                Team::Green => engines[1],
+            };
+            let control = SearchControl {
+                stop: &stop,
+                deadline: config.move_time.map(|x| Instant::now() + x),
+            };
+            iterative_deepening(&board, engine, evaluator, orderer, None, &control, |_| {})?
+                .octi_move()
+        };
+
+        match octi_move {
+            Some(octi_move) => state.make_move(&octi_move)?,
+            // the side to move is stuck
+            None => {
+                return Ok(GameResult::Win(match board.turn() {
+                    Team::Red => Team::Green,
+                    Team::Green => Team::Red,
+                }))
+            }
+        }
+    }
+
+    unreachable!()
+}
+
+impl MatchResult {
+    // Getters
+
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+
+    // the share of the points, a draw is half a point
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    // the elo difference the score corresponds to, None while it is all wins or all losses
+    pub fn elo(&self) -> Option<f64> {
+        let score = self.score();
+        if score <= 0.0 || score >= 1.0 {
+            return None;
+        }
+        Some(-400.0 * (1.0 / score - 1.0).log10())
+    }
+}
+
+impl Display for MatchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "+{} -{} ={} ({:.1}%",
+            self.wins,
+            self.losses,
+            self.draws,
+            self.score() * 100.0
+        )?;
+        match self.elo() {
+            Some(elo) => write!(f, ", {:+.0} elo)", elo),
+            None => write!(f, ")"),
+        }
+    }
+}
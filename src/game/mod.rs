@@ -1,10 +1,31 @@
+pub mod analysis;
+pub mod background;
+pub mod matchup;
 mod parse;
+pub mod record;
+pub mod selfplay;
 
 use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-use super::ai::board;
-use super::ai::minimax;
-use super::board::{Board, BoardEventProcessor, OctiMove};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::ai::board::{self, Symmetry};
+use super::ai::book::Book;
+use super::ai::eval::{explain_eval, EvalData, Evaluator, Value};
+use super::ai::mcts::{MctsConfig, Playout};
+use super::ai::network::Network;
+use super::ai::priority::PriorityEvalData;
+use super::ai::skill::{pick_move, Skill};
+use super::ai::tablebase::{Tablebase, TbValue};
+use super::ai::{multi_pv, search, winner, Engine, MinimaxConfig};
+use super::board::{Board, BoardEventProcessor, Boardable, OctiMove, Team};
+
+use analysis::{analyze, Annotation, Limit};
+use background::{BackgroundSearch, Finished};
+use record::{write_records, GameRecord, GameResult};
 
 // Aliases
 
@@ -18,17 +39,79 @@ pub enum Action {
     Forward(usize),
     Backward(usize),
     OctiMove(OctiMove),
-    AI(u32),
-    Ovewrite
+    AI(Skill),
+    Mcts(u32, Playout),
+    Ovewrite,
+    LoadEvalData(PathBuf),
+    LoadNetwork(PathBuf),
+    LoadPriorityEvalData(PathBuf),
+    LoadBook(PathBuf),
+    LoadTablebase(PathBuf),
+    Save(PathBuf),
+    Hint(usize),
+    Eval,
+    Seed(u64),
+    Analyze(Limit),
+    Ponder(bool),
 }
 
+// the evaluator the ai plays with, the last loaded of the eval data and a network
+#[derive(Clone)]
+pub enum GameEvaluator {
+    Linear(Box<EvalData>),
+    Network(Arc<Network>),
+}
+
+// Consts
+
+// depth of the search hint falls back on outside the tablebase
+const HINT_DEPTH: u32 = 4;
+
 // Structs
 
+// a search of the position after the reply the engine expects, started right after it moved
+//
+// it only saves time on an exact hit, the searches don't share their tables, like the depths of
+// iterative deepening don't, so after any other reply or at another depth its work is lost
+struct Ponder {
+    // the position the reply is expected in
+    from: board::Board,
+    // the position after the reply
+    position: board::Board,
+    search: BackgroundSearch,
+}
+
 pub struct Game {
     state: Board,
     start: Board,
     history: GameHistory,
+    // one per move of the history, filled in by analyze
+    annotations: Vec<Option<Annotation>>,
     cursor: usize,
+    eval_data: EvalData,
+    network: Option<Arc<Network>>,
+    priority_eval_data: PriorityEvalData,
+    book: Option<Book>,
+    tablebase: Option<Arc<Tablebase>>,
+    rng: StdRng,
+    pondering: bool,
+    ponder: Option<Ponder>,
+}
+
+impl Evaluator for GameEvaluator {
+    fn evaluate(&self, board: &board::Board) -> Value {
+        match self {
+            GameEvaluator::Linear(eval_data) => eval_data.evaluate(board),
+            GameEvaluator::Network(network) => network.evaluate(board),
+        }
+    }
+
+    fn symmetries(&self) -> Vec<Symmetry> {
+        match self {
+            GameEvaluator::Linear(eval_data) => eval_data.symmetries(),
+            GameEvaluator::Network(network) => network.symmetries(),
+        }
+    }
 }
 
 impl Game {
@@ -37,7 +120,16 @@ impl Game {
             state: board.clone(),
             start: board,
             history: Vec::new(),
+            annotations: Vec::new(),
             cursor: 0,
+            eval_data: EvalData::default(),
+            network: None,
+            priority_eval_data: PriorityEvalData::default(),
+            book: None,
+            tablebase: None,
+            rng: StdRng::from_entropy(),
+            pondering: true,
+            ponder: None,
         }
     }
 
@@ -59,6 +151,51 @@ impl Game {
         self.cursor
     }
 
+    pub fn evaluator(&self) -> GameEvaluator {
+        match &self.network {
+            Some(network) => GameEvaluator::Network(network.clone()),
+            None => GameEvaluator::Linear(Box::new(self.eval_data.clone())),
+        }
+    }
+
+    // Setters
+
+    // unloads the network, so the ai plays with the eval data again
+    pub fn set_eval_data(&mut self, eval_data: EvalData) {
+        self.eval_data = eval_data;
+        self.network = None;
+    }
+
+    pub fn set_network(&mut self, network: Network) {
+        self.network = Some(Arc::new(network));
+    }
+
+    pub fn set_priority_eval_data(&mut self, priority_eval_data: PriorityEvalData) {
+        self.priority_eval_data = priority_eval_data;
+    }
+
+    pub fn set_book(&mut self, book: Book) {
+        self.book = Some(book);
+    }
+
+    pub fn set_tablebase(&mut self, tablebase: Tablebase) {
+        self.tablebase = Some(Arc::new(tablebase));
+    }
+
+    // whether the engine searches on the opponent's time after a background search, which only
+    // helps when the opponent plays the expected reply
+    pub fn set_pondering(&mut self, pondering: bool) {
+        self.pondering = pondering;
+        if !pondering {
+            self.stop_pondering();
+        }
+    }
+
+    // makes the book and skill level picks reproducible
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
     // Operations
 
     pub fn make_move(&mut self, octi_move: OctiMove) -> Result<(), String> {
@@ -69,49 +206,442 @@ impl Game {
         self.state.make_move(&octi_move)?;
 
         self.history.push(octi_move);
+        self.annotations.push(None);
         self.cursor += 1;
+        self.check_ponder();
 
         Ok(())
     }
 
-    pub fn ai(&mut self, depth: u32) -> Result<(), Box<dyn Error>> {
+    // plays a move of the opening book if the position is in it
+    fn play_book_move(&mut self) -> Result<bool, String> {
+        let book_move = self
+            .book
+            .as_ref()
+            .and_then(|book| book.pick(&self.state, &mut self.rng));
+        match book_move {
+            Some(octi_move) => {
+                self.make_move(octi_move)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // plays a book move if the position is in the opening book, searches otherwise
+    pub fn ai(&mut self, engine: &Engine) -> Result<(), Box<dyn Error>> {
+        if self.play_book_move()? {
+            return Ok(());
+        }
+
         let board = board::Board::new(self.state());
-        let octi_move = minimax(&board, depth)?
-            .octi_move()
+        let octi_move = search(
+            &board,
+            engine,
+            &self.evaluator(),
+            &self.priority_eval_data,
+            self.tablebase.as_deref(),
+        )?
+        .octi_move()
             .ok_or("No possible moves from possition")?;
         self.make_move(octi_move)?;
         Ok(())
     }
 
-    pub fn process_action(&mut self, action: Action) -> Result<(), String> {
-        match action {
+    // like ai with a depth, but the search runs on another thread, None if a book move was played
+    // right away
+    //
+    // if the opponent played the expected reply and the depth is the one pondered at, the ponder
+    // search is carried on, otherwise it is aborted and the search starts over
+    pub fn start_ai(&mut self, depth: u32) -> Result<Option<BackgroundSearch>, Box<dyn Error>> {
+        if depth == 0 {
+            Err("Minimax cannot be depth 0")?;
+        }
+
+        if let Some(ponder) = self.ponder.take() {
+            if ponder.position == board::Board::new(self.state())
+                && ponder.search.depth() == Some(depth)
+            {
+                return Ok(Some(ponder.search));
+            }
+            ponder.search.abort();
+        }
+
+        if self.play_book_move()? {
+            return Ok(None);
+        }
+
+        Ok(Some(BackgroundSearch::start(
+            board::Board::new(self.state()),
+            MinimaxConfig {
+                depth,
+                ..MinimaxConfig::default()
+            },
+            self.evaluator(),
+            self.priority_eval_data.clone(),
+            self.tablebase.clone(),
+        )))
+    }
+
+    // like ai_skill, but the search runs on another thread, None if a book move was played right
+    // away
+    pub fn start_ai_skill(
+        &mut self,
+        skill: Skill,
+    ) -> Result<Option<BackgroundSearch>, Box<dyn Error>> {
+        if let Skill::Depth(depth) = skill {
+            return self.start_ai(depth);
+        }
+
+        let board = board::Board::new(self.state());
+        let evaluator = self.evaluator();
+        let priority_eval_data = self.priority_eval_data.clone();
+        let tablebase = self.tablebase.clone();
+        // drawn here, so the pick stays reproducible with a seed
+        let mut rng = StdRng::seed_from_u64(self.rng.gen());
+        Ok(Some(BackgroundSearch::run(move || {
+            let octi_move = pick_move(
+                &board,
+                &skill,
+                &evaluator,
+                &priority_eval_data,
+                tablebase.as_deref(),
+                &mut rng,
+            )
+            .map_err(|e| e.to_string())?;
+            Ok(Finished::Line(octi_move.into_iter().collect()))
+        })))
+    }
+
+    // like ai with mcts, but the search runs on another thread, None if a book move was played
+    // right away
+    pub fn start_mcts(
+        &mut self,
+        config: MctsConfig,
+    ) -> Result<Option<BackgroundSearch>, Box<dyn Error>> {
+        if self.play_book_move()? {
+            return Ok(None);
+        }
+
+        let board = board::Board::new(self.state());
+        let evaluator = self.evaluator();
+        let priority_eval_data = self.priority_eval_data.clone();
+        let tablebase = self.tablebase.clone();
+        Ok(Some(BackgroundSearch::run(move || {
+            let octi_move = search(
+                &board,
+                &Engine::Mcts(config),
+                &evaluator,
+                &priority_eval_data,
+                tablebase.as_deref(),
+            )
+            .map_err(|e| e.to_string())?
+            .octi_move();
+            Ok(Finished::Line(octi_move.into_iter().collect()))
+        })))
+    }
+
+    // like hint, but the search runs on another thread
+    pub fn start_hint(&self, count: usize) -> BackgroundSearch {
+        let board = board::Board::new(self.state());
+        let evaluator = self.evaluator();
+        let priority_eval_data = self.priority_eval_data.clone();
+        let tablebase = self.tablebase.clone();
+        BackgroundSearch::run(move || {
+            hint(
+                &board,
+                count,
+                &evaluator,
+                &priority_eval_data,
+                tablebase.as_deref(),
+            )
+            .map(Finished::Message)
+            .map_err(|e| e.to_string())
+        })
+    }
+
+    // like analyze, but the searches run on another thread
+    pub fn start_analyze(&self, limit: Limit) -> BackgroundSearch {
+        let start = self.start.clone();
+        let history = self.history.clone();
+        let evaluator = self.evaluator();
+        let priority_eval_data = self.priority_eval_data.clone();
+        let tablebase = self.tablebase.clone();
+        BackgroundSearch::run(move || {
+            analyze(
+                &start,
+                &history,
+                limit,
+                &evaluator,
+                &priority_eval_data,
+                tablebase.as_deref(),
+            )
+            .map(Finished::Annotations)
+            .map_err(|e| e.to_string())
+        })
+    }
+
+    // applies what a background search found, the game must not have changed since it started,
+    // after playing a move of the ai it ponders on the reply the search expects
+    pub fn finish_search(&mut self, search: BackgroundSearch) -> Result<Option<String>, String> {
+        let depth = search.depth();
+        let pv = match search.join()? {
+            Finished::Line(pv) => pv,
+            Finished::Message(message) => return Ok(Some(message)),
+            Finished::Annotations(annotations) => return Ok(Some(self.annotate(annotations))),
+        };
+        let octi_move = pv.first().ok_or("No possible moves from possition")?;
+        self.make_move(octi_move.clone())?;
+
+        // only iterative deepening searches have a line to ponder on
+        let (reply, depth) = match (pv.get(1), depth) {
+            (Some(reply), Some(depth)) if self.pondering => (reply, depth),
+            _ => return Ok(None),
+        };
+        let mut position = self.state.clone();
+        if position.make_move(reply).is_err() {
+            return Ok(None);
+        }
+        let position = board::Board::new(&position);
+        if winner(&position).is_some() {
+            return Ok(None);
+        }
+
+        let search = BackgroundSearch::start(
+            position,
+            MinimaxConfig {
+                depth,
+                ..MinimaxConfig::default()
+            },
+            self.evaluator(),
+            self.priority_eval_data.clone(),
+            self.tablebase.clone(),
+        );
+        self.ponder = Some(Ponder {
+            from: board::Board::new(self.state()),
+            position,
+            search,
+        });
+        Ok(Some(format!("Expecting {}, pondering", reply)))
+    }
+
+    // pondering stops once the game leaves the expected line
+    fn check_ponder(&mut self) {
+        let state = board::Board::new(self.state());
+        let expected = self
+            .ponder
+            .as_ref()
+            .is_some_and(|x| state == x.from || state == x.position);
+        if !expected {
+            self.stop_pondering();
+        }
+    }
+
+    fn stop_pondering(&mut self) {
+        // the search thread is left to wind down on its own
+        if let Some(ponder) = self.ponder.take() {
+            ponder.search.abort();
+        }
+    }
+
+    // full strength plays like ai, the lower levels leave the book out as book moves are full
+    // strength play
+    pub fn ai_skill(&mut self, skill: &Skill) -> Result<(), Box<dyn Error>> {
+        if let Skill::Depth(depth) = skill {
+            return self.ai(&Engine::Minimax(MinimaxConfig {
+                depth: *depth,
+                ..MinimaxConfig::default()
+            }));
+        }
+
+        let board = board::Board::new(self.state());
+        let octi_move = pick_move(
+            &board,
+            skill,
+            &self.evaluator(),
+            &self.priority_eval_data,
+            self.tablebase.as_deref(),
+            &mut self.rng,
+        )?
+        .ok_or("No possible moves from possition")?;
+        self.make_move(octi_move)?;
+        Ok(())
+    }
+
+    // the count best moves, with exact tablebase results if the position is in the tablebase and
+    // search results with the lines expected to follow otherwise
+    pub fn hint(&self, count: usize) -> Result<String, Box<dyn Error>> {
+        hint(
+            &board::Board::new(self.state()),
+            count,
+            &self.evaluator(),
+            &self.priority_eval_data,
+            self.tablebase.as_deref(),
+        )
+    }
+
+    // annotates every move of the history, the flagged moves are listed
+    pub fn analyze(&mut self, limit: Limit) -> Result<String, Box<dyn Error>> {
+        let annotations = analyze(
+            &self.start,
+            &self.history,
+            limit,
+            &self.evaluator(),
+            &self.priority_eval_data,
+            self.tablebase.as_deref(),
+        )?;
+        Ok(self.annotate(annotations))
+    }
+
+    // keeps the annotations of every move of the history, the flagged moves are listed
+    fn annotate(&mut self, annotations: Vec<Annotation>) -> String {
+        self.annotations = annotations.into_iter().map(Some).collect();
+
+        let mut lines = vec![];
+        for ply in 0..self.history.len() {
+            if let Some(annotation) = self.annotations[ply].filter(|x| x.judgement().is_some()) {
+                lines.push(self.describe_move(ply, &annotation));
+            }
+        }
+        if lines.is_empty() {
+            lines.push(String::from("No inaccuracies"));
+        }
+        lines.join("\n")
+    }
+
+    // the annotation of the move leading to the cursor, if it was analyzed
+    pub fn cursor_annotation(&self) -> Option<String> {
+        let ply = self.cursor.checked_sub(1)?;
+        let annotation = self.annotations[ply]?;
+        Some(self.describe_move(ply, &annotation))
+    }
+
+    fn describe_move(&self, ply: usize, annotation: &Annotation) -> String {
+        let mut description = format!(
+            "{}. {}: {} -> {}",
+            ply + 1,
+            self.history[ply],
+            annotation.before(),
+            annotation.after()
+        );
+        if let Some(judgement) = annotation.judgement() {
+            description = format!("{} {}", description, judgement);
+        }
+        description
+    }
+
+    // the terms the static evaluation of the current position is made of, the ai doesn't use them
+    // once a network is loaded
+    pub fn explain_eval(&self) -> String {
+        let board = board::Board::new(self.state());
+        let explanation = explain_eval(&board, &self.eval_data).to_string();
+        match &self.network {
+            Some(network) => format!("{}\nnetwork {}", explanation, network.evaluate(&board)),
+            None => explanation,
+        }
+    }
+
+    // actions can leave a message for the player
+    pub fn process_action(&mut self, action: Action) -> Result<Option<String>, String> {
+        // moving the cursor shows the annotation of the move it lands after
+        let result = match action {
             Action::Start => {
                 self.cursor_start();
-                Ok(())
+                return Ok(self.cursor_annotation());
             }
             Action::End => {
                 self.cursor_end();
-                Ok(())
+                return Ok(self.cursor_annotation());
             }
             Action::Forward(steps) => {
                 self.move_cursor_forward(steps);
-                Ok(())
+                return Ok(self.cursor_annotation());
             }
             Action::Backward(steps) => {
                 self.move_cursor_backwords(steps);
-                Ok(())
+                return Ok(self.cursor_annotation());
             }
             Action::OctiMove(octi_move) => self.make_move(octi_move),
-            Action::AI(depth) => self.ai(depth).map_err(|e| e.to_string()),
+            Action::AI(skill) => self.ai_skill(&skill).map_err(|e| e.to_string()),
+            Action::Mcts(iterations, playout) => self
+                .ai(&Engine::Mcts(MctsConfig {
+                    iterations,
+                    playout,
+                    ..MctsConfig::default()
+                }))
+                .map_err(|e| e.to_string()),
             Action::Ovewrite => {
                 self.overwrite_history();
                 Ok(())
             }
+            Action::LoadEvalData(path) => {
+                let eval_data = EvalData::from_file(path).map_err(|e| e.to_string())?;
+                let message = match self.network {
+                    Some(_) => "The ai evaluates with the eval data, the network is unloaded",
+                    None => "The ai evaluates with the eval data",
+                };
+                self.set_eval_data(eval_data);
+                return Ok(Some(String::from(message)));
+            }
+            Action::LoadNetwork(path) => {
+                self.set_network(Network::from_file(path).map_err(|e| e.to_string())?);
+                return Ok(Some(String::from("The ai evaluates with the network")));
+            }
+            Action::LoadPriorityEvalData(path) => {
+                self.priority_eval_data =
+                    PriorityEvalData::from_file(path).map_err(|e| e.to_string())?;
+                Ok(())
+            }
+            Action::LoadBook(path) => {
+                self.book = Some(Book::from_file(path).map_err(|e| e.to_string())?);
+                Ok(())
+            }
+            Action::LoadTablebase(path) => {
+                self.tablebase = Some(Arc::new(
+                    Tablebase::from_file(path).map_err(|e| e.to_string())?,
+                ));
+                Ok(())
+            }
+            Action::Save(path) => {
+                write_records(path, &[self.record()]).map_err(|e| e.to_string())
+            }
+            Action::Hint(count) => return self.hint(count).map(Some).map_err(|e| e.to_string()),
+            Action::Eval => return Ok(Some(self.explain_eval())),
+            Action::Seed(seed) => {
+                self.set_seed(seed);
+                Ok(())
+            }
+            Action::Ponder(pondering) => {
+                self.set_pondering(pondering);
+                Ok(())
+            }
+            Action::Analyze(limit) => {
+                return self.analyze(limit).map(Some).map_err(|e| e.to_string())
+            }
+        };
+
+        result.map(|_| None)
+    }
+
+    // the whole history, regardless of the cursor
+    pub fn record(&self) -> GameRecord {
+        let mut end = self.start.clone();
+        for octi_move in &self.history {
+            end.make_move(octi_move).unwrap();
         }
+
+        let result = match winner(&board::Board::new(&end)) {
+            Some(team) => GameResult::Win(team),
+            None => GameResult::Unfinished,
+        };
+        let mut record = GameRecord::new(result, self.history.clone());
+        record.set_annotations(self.annotations.clone());
+        record
     }
 
     pub fn overwrite_history(&mut self) {
         self.history.truncate(self.cursor);
+        self.annotations.truncate(self.cursor);
     }
 
     pub fn move_cursor_forward(&mut self, by: usize) {
@@ -121,6 +651,7 @@ impl Game {
         }
 
         self.cursor = new_cursor;
+        self.check_ponder();
     }
 
     pub fn move_cursor_backwords(&mut self, by: usize) {
@@ -132,6 +663,7 @@ impl Game {
         }
 
         self.cursor = new_cursor;
+        self.check_ponder();
     }
 
     pub fn cursor_end(&mut self) {
@@ -153,3 +685,65 @@ impl Default for Game {
         Game::new(Board::default())
     }
 }
+
+// the count best moves like Game::hint, without the game so it can run on another thread
+fn hint(
+    board: &board::Board,
+    count: usize,
+    evaluator: &GameEvaluator,
+    priority_eval_data: &PriorityEvalData,
+    tablebase: Option<&Tablebase>,
+) -> Result<String, Box<dyn Error>> {
+    let turn = board.turn();
+
+    if let Some(scores) = tablebase.and_then(|x| x.score_moves(board)) {
+        let opponent = match turn {
+            Team::Red => Team::Green,
+            Team::Green => Team::Red,
+        };
+        let mut lines = vec![String::from("Tablebase")];
+        for (i, (octi_move, value)) in scores.iter().take(count).enumerate() {
+            let result = match value {
+                TbValue::Win(d) => format!("{:?} wins in {} plies", turn, d),
+                TbValue::Loss(d) => format!("{:?} wins in {} plies", opponent, d),
+                TbValue::Draw => String::from("Draw"),
+            };
+            lines.push(format!("{}. {}: {}", i + 1, result, octi_move));
+        }
+        if lines.len() == 1 {
+            Err("No possible moves from possition")?;
+        }
+        return Ok(lines.join("\n"));
+    }
+
+    let variations = multi_pv(
+        board,
+        &MinimaxConfig {
+            depth: HINT_DEPTH,
+            ..MinimaxConfig::default()
+        },
+        evaluator,
+        priority_eval_data,
+        tablebase,
+        count,
+    )?;
+    if variations.is_empty() {
+        Err("No possible moves from possition")?;
+    }
+
+    let mut lines = vec![format!("Depth {}", HINT_DEPTH)];
+    for (i, variation) in variations.iter().enumerate() {
+        let octi_moves = variation
+            .octi_moves()
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        lines.push(format!(
+            "{}. {}: {}",
+            i + 1,
+            variation.score(),
+            octi_moves.join(", ")
+        ));
+    }
+    Ok(lines.join("\n"))
+}
@@ -1,6 +1,8 @@
+use super::super::ai::mcts::Playout;
 use super::super::board::OctiMove;
 use super::Action;
 
+use std::path::PathBuf;
 use std::str::FromStr;
 
 // impl Display for Action
@@ -42,13 +44,95 @@ impl FromStr for Action {
                     Err(format!("Invalid number of arguments: {}", args.len()))?;
                 }
 
-                Ok(Action::AI(
-                    args[1].parse().map_err(|_| "Invalid AI argument")?,
+                // a skill level or a search depth
+                Ok(Action::AI(args[1].parse()?))
+            }
+            "mcts" => {
+                if args.len() != 2 && args.len() != 3 {
+                    Err(format!("Invalid number of arguments: {}", args.len()))?;
+                }
+
+                let playout = match args.get(2) {
+                    None | Some(&"priority") => Playout::Priority,
+                    Some(&"random") => Playout::Random,
+                    Some(other) => Err(format!("Invalid playout: {}", other))?,
+                };
+
+                Ok(Action::Mcts(
+                    args[1].parse().map_err(|_| "Invalid MCTS argument")?,
+                    playout,
                 ))
             }
             "overwrite" => {
                 Ok(Action::Ovewrite)
             }
+            "load" => {
+                if args.len() < 3 {
+                    Err(format!("Invalid number of arguments: {}", args.len()))?;
+                }
+
+                // paths may contain spaces
+                let path = PathBuf::from(args[2..].join(" "));
+                match args[1] {
+                    "eval" => Ok(Action::LoadEvalData(path)),
+                    "network" => Ok(Action::LoadNetwork(path)),
+                    "priority" => Ok(Action::LoadPriorityEvalData(path)),
+                    "book" => Ok(Action::LoadBook(path)),
+                    "tablebase" => Ok(Action::LoadTablebase(path)),
+                    _ => Err(format!("Unrecognized load type: {}", args[1])),
+                }
+            }
+            "save" => {
+                if args.len() < 2 {
+                    Err(format!("Invalid number of arguments: {}", args.len()))?;
+                }
+
+                Ok(Action::Save(PathBuf::from(args[1..].join(" "))))
+            }
+            "hint" => {
+                if args.len() > 2 {
+                    Err(format!("Invalid number of arguments: {}", args.len()))?;
+                }
+
+                // the best move only by default
+                let count = match args.get(1) {
+                    Some(count) => count.parse().map_err(|_| "Invalid hint argument")?,
+                    None => 1,
+                };
+                if count == 0 {
+                    Err("Hint needs at least one move")?;
+                }
+                Ok(Action::Hint(count))
+            }
+            "eval" => Ok(Action::Eval),
+            "analyze" => {
+                if args.len() != 2 {
+                    Err(format!("Invalid number of arguments: {}", args.len()))?;
+                }
+
+                // a depth or a time per position
+                Ok(Action::Analyze(args[1].parse()?))
+            }
+            "ponder" => {
+                if args.len() != 2 {
+                    Err(format!("Invalid number of arguments: {}", args.len()))?;
+                }
+
+                match args[1] {
+                    "on" => Ok(Action::Ponder(true)),
+                    "off" => Ok(Action::Ponder(false)),
+                    _ => Err(format!("Invalid ponder argument: {}", args[1])),
+                }
+            }
+            "seed" => {
+                if args.len() != 2 {
+                    Err(format!("Invalid number of arguments: {}", args.len()))?;
+                }
+
+                Ok(Action::Seed(
+                    args[1].parse().map_err(|_| "Invalid seed argument")?,
+                ))
+            }
             _ => Err(format!("Unrecognized move type: {}", args[0])),
         }
     }
@@ -0,0 +1,188 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::super::board::{Board, BoardEventProcessor, OctiMove, Team};
+use super::analysis::Annotation;
+
+// a game record file holds any number of games separated by empty lines, every game starts with
+// its result followed by its moves from the default board, one per line:
+//
+// result red
+// arr (1,5) 2
+// mov (1,5) 2
+//
+// result unfinished
+// ...
+//
+// analyzed moves are followed by their annotation:
+//
+// mov (1,5) 2 # 31 -250 blunder
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    Win(Team),
+    Draw,
+    Unfinished,
+}
+
+#[derive(Clone)]
+pub struct GameRecord {
+    result: GameResult,
+    moves: Vec<OctiMove>,
+    // one per move
+    annotations: Vec<Option<Annotation>>,
+}
+
+impl GameRecord {
+    pub fn new(result: GameResult, moves: Vec<OctiMove>) -> GameRecord {
+        let annotations = vec![None; moves.len()];
+        GameRecord {
+            result,
+            moves,
+            annotations,
+        }
+    }
+
+    // Getters
+
+    pub fn result(&self) -> GameResult {
+        self.result
+    }
+
+    pub fn moves(&self) -> &[OctiMove] {
+        &self.moves
+    }
+
+    // Setters
+
+    pub fn set_annotations(&mut self, annotations: Vec<Option<Annotation>>) {
+        self.annotations = annotations;
+    }
+
+    // Operations
+
+    // every position of the game, starting with the default board
+    pub fn positions(&self) -> Result<Vec<Board>, String> {
+        let mut board = Board::default();
+        let mut positions = Vec::with_capacity(self.moves.len() + 1);
+        positions.push(board.clone());
+
+        for (ply, octi_move) in self.moves.iter().enumerate() {
+            board
+                .make_move(octi_move)
+                .map_err(|e| format!("Illegal move {} at ply {}: {}", octi_move, ply, e))?;
+            positions.push(board.clone());
+        }
+
+        Ok(positions)
+    }
+}
+
+pub fn read_records<P: AsRef<Path>>(path: P) -> Result<Vec<GameRecord>, Box<dyn Error>> {
+    let path = path.as_ref();
+    let s =
+        fs::read_to_string(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+
+    let mut records = Vec::new();
+    for (i, game) in s.split("\n\n").enumerate() {
+        if game.trim().is_empty() {
+            continue;
+        }
+
+        records.push(
+            game.parse::<GameRecord>()
+                .map_err(|e| format!("Invalid game {} in {}: {}", i, path.display(), e))?,
+        );
+    }
+
+    Ok(records)
+}
+
+pub fn write_records<P: AsRef<Path>>(
+    path: P,
+    records: &[GameRecord],
+) -> Result<(), Box<dyn Error>> {
+    let s = records
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    fs::write(path, s)?;
+    Ok(())
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameResult::Win(Team::Red) => write!(f, "red"),
+            GameResult::Win(Team::Green) => write!(f, "green"),
+            GameResult::Draw => write!(f, "draw"),
+            GameResult::Unfinished => write!(f, "unfinished"),
+        }
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "red" => Ok(GameResult::Win(Team::Red)),
+            "green" => Ok(GameResult::Win(Team::Green)),
+            "draw" => Ok(GameResult::Draw),
+            "unfinished" => Ok(GameResult::Unfinished),
+            _ => Err(format!("Unrecognized result: {}", s)),
+        }
+    }
+}
+
+impl Display for GameRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "result {}", self.result)?;
+        for (octi_move, annotation) in self.moves.iter().zip(&self.annotations) {
+            match annotation {
+                Some(annotation) => writeln!(f, "{} # {}", octi_move, annotation)?,
+                None => writeln!(f, "{}", octi_move)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().map(|x| x.trim()).filter(|x| !x.is_empty());
+
+        let header = lines.next().ok_or("Empty game")?;
+        let result = header
+            .strip_prefix("result ")
+            .ok_or_else(|| format!("Expected result line, got: {}", header))?
+            .parse()?;
+
+        let mut moves = Vec::new();
+        let mut annotations = Vec::new();
+        for line in lines {
+            match line.split_once(" # ") {
+                Some((octi_move, annotation)) => {
+                    moves.push(octi_move.parse::<OctiMove>()?);
+                    annotations.push(Some(annotation.parse::<Annotation>()?));
+                }
+                None => {
+                    moves.push(line.parse::<OctiMove>()?);
+                    annotations.push(None);
+                }
+            }
+        }
+
+        Ok(GameRecord {
+            result,
+            moves,
+            annotations,
+        })
+    }
+}
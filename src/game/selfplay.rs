@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use super::super::ai::board;
+use super::super::ai::eval::{Evaluator, Value};
+use super::super::ai::priority::MoveOrderer;
+use super::super::ai::{legal_moves, search, winner, Engine, MinimaxConfig};
+use super::super::board::{Board, BoardEventProcessor, Boardable, Team};
+use super::record::GameResult;
+
+// a self-play file has one line per position, with tab separated fields:
+//
+// game  ply  plies  position  score  result
+//
+// plies is the length of the whole game, so a game is complete once it has plies + 1 lines, the
+// score is the search score of the position (from red's perspective) and the result is the final
+// result of the game
+
+pub struct SelfPlayConfig {
+    pub games: usize,
+    pub depth: u32,
+    // the first random_plies moves of every game are picked uniformly at random
+    pub random_plies: usize,
+    // games still going after max_plies are called a draw
+    pub max_plies: usize,
+    pub threads: usize,
+    // game i is played with seed + i, so a resumed run plays the same games
+    pub seed: u64,
+}
+
+#[derive(Clone)]
+pub struct PositionRecord {
+    game: usize,
+    ply: usize,
+    plies: usize,
+    position: Board,
+    score: Value,
+    result: GameResult,
+}
+
+impl PositionRecord {
+    // Getters
+
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    pub fn position(&self) -> &Board {
+        &self.position
+    }
+
+    pub fn result(&self) -> GameResult {
+        self.result
+    }
+}
+
+// plays the games of the config missing from path and appends them to it, games that were
+// interrupted halfway are dropped and replayed
+pub fn generate<E, O, F>(
+    path: &Path,
+    config: &SelfPlayConfig,
+    evaluator: &E,
+    orderer: &O,
+    mut progress: F,
+) -> Result<(), Box<dyn Error>>
+where
+    E: Evaluator + Sync,
+    O: MoveOrderer + Sync,
+    F: FnMut(usize, usize),
+{
+    let completed = resume(path)?;
+    let pending = (0..config.games)
+        .filter(|x| !completed.contains(x))
+        .collect::<Vec<_>>();
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| -> Result<(), Box<dyn Error>> {
+        for _ in 0..config.threads.max(1) {
+            let sender = sender.clone();
+            let (next, pending) = (&next, &pending);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(&game) = pending.get(i) else {
+                    break;
+                };
+                let records = play_game(game, config, evaluator, orderer);
+                if sender.send(records).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut done = completed.len();
+        progress(done, config.games);
+        for records in receiver {
+            // a game is written in one go so an interruption can only cut its last line
+            let lines = records
+                .iter()
+                .map(|x| format!("{}\n", x))
+                .collect::<String>();
+            file.write_all(lines.as_bytes())?;
+            file.flush()?;
+
+            done += 1;
+            progress(done, config.games);
+        }
+
+        Ok(())
+    })
+}
+
+pub fn read_positions<P: AsRef<Path>>(path: P) -> Result<Vec<PositionRecord>, Box<dyn Error>> {
+    let path = path.as_ref();
+    let s =
+        fs::read_to_string(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+
+    s.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            line.parse::<PositionRecord>()
+                .map_err(|e| format!("Invalid line {} in {}: {}", i + 1, path.display(), e).into())
+        })
+        .collect()
+}
+
+// rewrites path without incomplete games and returns the games it holds
+fn resume(path: &Path) -> Result<Vec<usize>, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let s = fs::read_to_string(path)?;
+    let records = s
+        .lines()
+        .filter_map(|x| x.parse::<PositionRecord>().ok())
+        .collect::<Vec<_>>();
+
+    let mut counts = HashMap::new();
+    for record in &records {
+        *counts.entry(record.game).or_insert(0) += 1;
+    }
+
+    let complete = |record: &PositionRecord| counts[&record.game] == record.plies + 1;
+    let lines = records
+        .iter()
+        .filter(|x| complete(x))
+        .map(|x| format!("{}\n", x))
+        .collect::<String>();
+    fs::write(path, lines)?;
+
+    let mut completed = records
+        .iter()
+        .filter(|x| complete(x))
+        .map(|x| x.game)
+        .collect::<Vec<_>>();
+    completed.dedup();
+    Ok(completed)
+}
+
+fn play_game<E: Evaluator, O: MoveOrderer>(
+    game: usize,
+    config: &SelfPlayConfig,
+    evaluator: &E,
+    orderer: &O,
+) -> Vec<PositionRecord> {
+    let mut rng = StdRng::seed_from_u64(config.seed.wrapping_add(game as u64));
+    let mut state = Board::default();
+    let mut records = Vec::new();
+    let engine = Engine::Minimax(MinimaxConfig {
+        depth: config.depth,
+        ..MinimaxConfig::default()
+    });
+
+    let result = loop {
+        let ply = records.len();
+        let board = board::Board::new(&state);
+
+        if let Some(team) = winner(&board) {
+            records.push(PositionRecord::new(game, ply, state, Value::Win(team)));
+            break GameResult::Win(team);
+        }
+
+        let result = search(&board, &engine, evaluator, orderer, None).unwrap();
+        records.push(PositionRecord::new(
+            game,
+            ply,
+            state.clone(),
+            result.score().value(),
+        ));
+
+        let octi_move = if ply < config.random_plies {
+            legal_moves(&board).choose(&mut rng).cloned()
+        } else {
+            result.octi_move()
+        };
+
+        match octi_move {
+            Some(octi_move) if ply < config.max_plies => state.make_move(&octi_move).unwrap(),
+            Some(_) => break GameResult::Draw,
+            // the side to move is stuck
+            None => {
+                break GameResult::Win(match board.turn() {
+                    Team::Red => Team::Green,
+                    Team::Green => Team::Red,
+                })
+            }
+        }
+    };
+
+    let plies = records.len() - 1;
+    for record in records.iter_mut() {
+        record.plies = plies;
+        record.result = result;
+    }
+    records
+}
+
+impl PositionRecord {
+    fn new(game: usize, ply: usize, position: Board, score: Value) -> PositionRecord {
+        PositionRecord {
+            game,
+            ply,
+            plies: 0,
+            position,
+            score,
+            result: GameResult::Unfinished,
+        }
+    }
+}
+
+impl Display for PositionRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.game, self.ply, self.plies, self.position, self.score, self.result
+        )
+    }
+}
+
+impl FromStr for PositionRecord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split('\t').collect();
+
+        if fields.len() != 6 {
+            Err(format!("Invalid number of fields: {}", fields.len()))?;
+        }
+
+        let number = |s: &str| {
+            s.parse::<usize>()
+                .map_err(|_| format!("Invalid number: {}", s))
+        };
+
+        Ok(PositionRecord {
+            game: number(fields[0])?,
+            ply: number(fields[1])?,
+            plies: number(fields[2])?,
+            position: fields[3].parse()?,
+            score: fields[4].parse()?,
+            result: fields[5].parse()?,
+        })
+    }
+}
@@ -1 +1,4 @@
 pub const ARROWS_PER_OCTI: usize = 8;
+// the largest board, the ai packs positions in arrays of this size
+pub const BOARD_WIDTH: usize = 6;
+pub const BOARD_HEIGHT: usize = 7;
@@ -1,11 +1,13 @@
-use std::io;
+use std::error::Error;
 
 mod ai;
 mod board;
+mod cli;
 mod game;
 mod global;
 mod ui;
 
-fn main() -> Result<(), io::Error> {
-    ui::run()
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    cli::run(&args)
 }
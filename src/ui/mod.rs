@@ -8,6 +8,7 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use std::io;
+use std::time::Duration;
 use tui::{
     backend::{Backend, CrosstermBackend},
     layout::Rect,
@@ -16,13 +17,20 @@ use tui::{
     Frame, Terminal,
 };
 
+use super::ai::mcts::MctsConfig;
 use super::board::Board;
+use super::game::background::BackgroundSearch;
 use super::game::{Action, Game};
 
+// how often the progress of a running search is checked
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 struct App {
     input: String,
     message: String,
     game: Game,
+    // the ai searching for its move, the game is left alone until it is done
+    search: Option<BackgroundSearch>,
 }
 
 impl App {
@@ -31,37 +39,101 @@ impl App {
     }
 }
 
-impl Default for App {
-    fn default() -> Self {
+impl App {
+    fn new(game: Game) -> App {
         App {
             input: String::new(),
             message: String::from("Press Esc to quit"),
-            game: Game::default(),
+            game,
+            search: None,
+        }
+    }
+
+    // a search the game started, None if the ai played a book move right away
+    fn start_search(&mut self, search: Result<Option<BackgroundSearch>, String>) {
+        match search {
+            Ok(Some(search)) => {
+                self.message = String::from(if search.stoppable() {
+                    "Searching, press Esc to stop"
+                } else {
+                    "Searching, press Esc to stop waiting"
+                });
+                self.search = Some(search);
+            }
+            Ok(None) => self.message.clear(),
+            Err(message) => self.message = message,
         }
     }
 }
 
-fn game_loop<B: Backend>(terminal: &mut Terminal<B>) -> Result<(), io::Error> {
-    let mut app = App::default();
+fn game_loop<B: Backend>(terminal: &mut Terminal<B>, game: Game) -> Result<(), io::Error> {
+    let mut app = App::new(game);
 
     loop {
+        if let Some(search) = &app.search {
+            if let Some(progress) = search.progress() {
+                app.message = progress;
+            }
+            if search.is_finished() {
+                let search = app.search.take().unwrap();
+                match app.game.finish_search(search) {
+                    Ok(Some(message)) | Err(message) => app.message = message,
+                    Ok(None) => app.message.clear(),
+                }
+            }
+        }
+
         terminal.draw(|f| render(f, &app))?;
 
+        // keep redrawing the progress of the search while no key is pressed
+        if app.search.is_some() && !event::poll(POLL_INTERVAL)? {
+            continue;
+        }
+
         if let Event::Key(key) = event::read()? {
             match key.code {
                 KeyCode::Enter => {
                     if app.input.is_empty() {
                         continue;
                     }
+                    if let Some(search) = &app.search {
+                        app.message = String::from(if search.stoppable() {
+                            "Searching, press Esc to play the best move so far"
+                        } else {
+                            "Searching, press Esc to stop waiting"
+                        });
+                        continue;
+                    }
 
                     let input: String = app.input.drain(..).collect();
                     let action = input.parse::<Action>();
                     match action {
+                        // searches run in the background, the rest is quick enough
+                        Ok(Action::AI(skill)) => {
+                            let search = app.game.start_ai_skill(skill);
+                            app.start_search(search.map_err(|e| e.to_string()));
+                        }
+                        Ok(Action::Mcts(iterations, playout)) => {
+                            let search = app.game.start_mcts(MctsConfig {
+                                iterations,
+                                playout,
+                                ..MctsConfig::default()
+                            });
+                            app.start_search(search.map_err(|e| e.to_string()));
+                        }
+                        Ok(Action::Hint(count)) => {
+                            let search = app.game.start_hint(count);
+                            app.start_search(Ok(Some(search)));
+                        }
+                        Ok(Action::Analyze(limit)) => {
+                            let search = app.game.start_analyze(limit);
+                            app.start_search(Ok(Some(search)));
+                        }
                         Ok(action) => match app.game.process_action(action) {
-                            Err(message) => {
+                            Ok(Some(message)) | Err(message) => {
                                 app.message = message;
                             }
-                            _ => app.message.clear(),
+                            Ok(None) => app.message.clear(),
                         },
                         Err(message) => {
                             app.message = message;
@@ -74,9 +146,16 @@ fn game_loop<B: Backend>(terminal: &mut Terminal<B>) -> Result<(), io::Error> {
                 KeyCode::Backspace => {
                     app.input.pop();
                 }
-                KeyCode::Esc => {
-                    return Ok(());
-                }
+                // stops the search first, it plays the best move found so far, the searches that
+                // can't be stopped are left to finish on their own and their result is dropped
+                KeyCode::Esc => match &app.search {
+                    Some(search) if search.stoppable() => search.abort(),
+                    Some(_) => {
+                        app.search = None;
+                        app.message = String::from("Stopped waiting for the search");
+                    }
+                    None => return Ok(()),
+                },
                 _ => {}
             }
         }
@@ -85,7 +164,7 @@ fn game_loop<B: Backend>(terminal: &mut Terminal<B>) -> Result<(), io::Error> {
 
 fn render<B: Backend>(f: &mut Frame<B>, app: &App) {
     let board_ui = board::BoardUI::new(app.board_state());
-    let layout = build_layout(f.size().width, &board_ui);
+    let layout = build_layout(f.size().width, &board_ui, &app.message);
 
     f.render_widget(board_ui, layout[0]);
 
@@ -104,7 +183,7 @@ fn render<B: Backend>(f: &mut Frame<B>, app: &App) {
     f.set_cursor(layout[1].x + app.input.len() as u16 + 1, layout[1].y + 1);
 }
 
-fn build_layout(width: u16, board_ui: &board::BoardUI) -> Vec<Rect> {
+fn build_layout(width: u16, board_ui: &board::BoardUI, message: &str) -> Vec<Rect> {
     let (board_width, board_height) = (board_ui.width(), board_ui.height());
 
     let x = (width - board_width) / 2;
@@ -114,18 +193,19 @@ fn build_layout(width: u16, board_ui: &board::BoardUI) -> Vec<Rect> {
     vstack.margin(2);
     vstack.push(3);
     vstack.margin(1);
-    vstack.push(2);
+    // messages like the eval breakdown span several lines
+    vstack.push(std::cmp::max(2, message.lines().count() as u16));
     vstack.layout()
 }
 
-pub fn run() -> Result<(), io::Error> {
+pub fn run(game: Game) -> Result<(), io::Error> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    game_loop(&mut terminal)?;
+    game_loop(&mut terminal, game)?;
 
     disable_raw_mode()?;
     execute!(